@@ -61,3 +61,131 @@ Create the name of the service account to use
 {{- default "default" .Values.serviceAccount.name }}
 {{- end }}
 {{- end }}
+
+{{/*
+Create the name of the secret with the mqtt password and influxdb token
+*/}}
+{{- define "jarvis-alpha-innotec-exporter.secretName" -}}
+{{- default (include "jarvis-alpha-innotec-exporter.fullname" .) .Values.config.existingSecret }}
+{{- end }}
+
+{{/*
+Container environment, shared by the cronjob and the daemon deployment
+*/}}
+{{- define "jarvis-alpha-innotec-exporter.env" -}}
+- name: RUST_LOG
+  value: {{ .Values.logLevel }}
+- name: TRANSPORT
+  valueFrom:
+    configMapKeyRef:
+      key: transport
+      name: {{ include "jarvis-alpha-innotec-exporter.fullname" . }}
+- name: WEBSOCKET_HOST_IP
+  valueFrom:
+    configMapKeyRef:
+      key: websocket-host-ip
+      name: {{ include "jarvis-alpha-innotec-exporter.fullname" . }}
+- name: WEBSOCKET_HOST_PORT
+  valueFrom:
+    configMapKeyRef:
+      key: websocket-host-port
+      name: {{ include "jarvis-alpha-innotec-exporter.fullname" . }}
+- name: WEBSOCKET_LOGIN_CODE
+  valueFrom:
+    configMapKeyRef:
+      key: websocket-login-code
+      name: {{ include "jarvis-alpha-innotec-exporter.fullname" . }}
+- name: WEBSOCKET_CONNECT_TIMEOUT_SECONDS
+  valueFrom:
+    configMapKeyRef:
+      key: websocket-connect-timeout-seconds
+      name: {{ include "jarvis-alpha-innotec-exporter.fullname" . }}
+- name: WEBSOCKET_RESPONSE_TIMEOUT_SECONDS
+  valueFrom:
+    configMapKeyRef:
+      key: websocket-response-timeout-seconds
+      name: {{ include "jarvis-alpha-innotec-exporter.fullname" . }}
+- name: WEBSOCKET_TOTAL_TIMEOUT_SECONDS
+  valueFrom:
+    configMapKeyRef:
+      key: websocket-total-timeout-seconds
+      name: {{ include "jarvis-alpha-innotec-exporter.fullname" . }}
+- name: WEBSOCKET_MAX_RETRIES
+  valueFrom:
+    configMapKeyRef:
+      key: websocket-max-retries
+      name: {{ include "jarvis-alpha-innotec-exporter.fullname" . }}
+- name: TCP_HOST_IP
+  valueFrom:
+    configMapKeyRef:
+      key: tcp-host-ip
+      name: {{ include "jarvis-alpha-innotec-exporter.fullname" . }}
+- name: TCP_HOST_PORT
+  valueFrom:
+    configMapKeyRef:
+      key: tcp-host-port
+      name: {{ include "jarvis-alpha-innotec-exporter.fullname" . }}
+- name: NATS_HOST
+  valueFrom:
+    configMapKeyRef:
+      name: {{ include "jarvis-alpha-innotec-exporter.fullname" . }}
+      key: nats-host
+- name: NATS_SUBJECT
+  valueFrom:
+    configMapKeyRef:
+      name: {{ include "jarvis-alpha-innotec-exporter.fullname" . }}
+      key: nats-subject
+- name: MQTT_HOST
+  valueFrom:
+    configMapKeyRef:
+      name: {{ include "jarvis-alpha-innotec-exporter.fullname" . }}
+      key: mqtt-host
+- name: MQTT_PORT
+  valueFrom:
+    configMapKeyRef:
+      name: {{ include "jarvis-alpha-innotec-exporter.fullname" . }}
+      key: mqtt-port
+- name: MQTT_USERNAME
+  valueFrom:
+    configMapKeyRef:
+      name: {{ include "jarvis-alpha-innotec-exporter.fullname" . }}
+      key: mqtt-username
+- name: MQTT_PASSWORD
+  valueFrom:
+    secretKeyRef:
+      name: {{ include "jarvis-alpha-innotec-exporter.secretName" . }}
+      key: mqtt-password
+      optional: true
+- name: MQTT_TOPIC_PREFIX
+  valueFrom:
+    configMapKeyRef:
+      name: {{ include "jarvis-alpha-innotec-exporter.fullname" . }}
+      key: mqtt-topic-prefix
+- name: MQTT_DISCOVERY_PREFIX
+  valueFrom:
+    configMapKeyRef:
+      name: {{ include "jarvis-alpha-innotec-exporter.fullname" . }}
+      key: mqtt-discovery-prefix
+- name: INFLUXDB_WRITE_URL
+  valueFrom:
+    configMapKeyRef:
+      name: {{ include "jarvis-alpha-innotec-exporter.fullname" . }}
+      key: influxdb-write-url
+- name: INFLUXDB_TOKEN
+  valueFrom:
+    secretKeyRef:
+      name: {{ include "jarvis-alpha-innotec-exporter.secretName" . }}
+      key: influxdb-token
+      optional: true
+- name: INFLUXDB_BATCH_SIZE
+  valueFrom:
+    configMapKeyRef:
+      name: {{ include "jarvis-alpha-innotec-exporter.fullname" . }}
+      key: influxdb-batch-size
+- name: MEASUREMENT_FILE_CONFIG_MAP_NAME
+  value: {{ include "jarvis-alpha-innotec-exporter.fullname" . }}
+{{- if .Values.config.navigationCache }}
+- name: NAVIGATION_CACHE_CONFIG_MAP_NAME
+  value: {{ include "jarvis-alpha-innotec-exporter.fullname" . }}-navigation-cache
+{{- end }}
+{{- end }}
@@ -0,0 +1,299 @@
+use crate::discovery::DEFAULT_ENTITY_NAME;
+use crate::model::Transport;
+use crate::tcp_client::TcpClientConfig;
+use crate::websocket_client::{Timeouts, WebsocketClientConfig};
+use clap::{Args, Parser, Subcommand};
+use std::error::Error;
+use std::time::Duration;
+
+// every flag falls back to the environment variable set by the helm chart, so a shell can override a single one
+#[derive(Parser, Debug)]
+#[command(
+    version,
+    about = "Exports measurements from an Alpha Innotec heatpump to jarvis"
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[arg(long, global = true, env = "TRANSPORT", default_value = "websocket", value_parser = parse_transport, help = "Protocol to talk to the controller, websocket or tcp")]
+    pub transport: Transport,
+
+    #[arg(
+        long,
+        global = true,
+        env = "CONFIG_PATH",
+        help = "Path of the config file with the sample configs"
+    )]
+    pub config: Option<String>,
+
+    #[command(flatten)]
+    pub websocket: WebsocketArgs,
+
+    #[command(flatten)]
+    pub tcp: TcpArgs,
+}
+
+impl Cli {
+    // without a subcommand the exporter scrapes once, as the cronjob runs it
+    pub fn command_or_default(&self) -> Command {
+        self.command
+            .clone()
+            .unwrap_or(Command::Run(RunArgs::default()))
+    }
+
+    // discover, validate, get and dump-page read pages by navigation path, which only the websocket has
+    pub fn websocket_config(&self) -> Result<WebsocketClientConfig, Box<dyn Error>> {
+        if self.transport != Transport::Websocket {
+            return Err(Box::<dyn Error>::from(
+                "This command is only supported by the websocket transport".to_string(),
+            ));
+        }
+
+        self.websocket.config()
+    }
+}
+
+#[derive(Subcommand, Debug, Clone, PartialEq)]
+pub enum Command {
+    #[command(about = "Scrapes once and publishes the measurement, the default")]
+    Run(RunArgs),
+    #[command(about = "Keeps scraping on an interval over an open session until terminated")]
+    Daemon {
+        #[arg(
+            long,
+            env = "SCRAPE_INTERVAL_SECONDS",
+            default_value_t = 60,
+            help = "Seconds between the start of two scrapes"
+        )]
+        scrape_interval_seconds: u64,
+    },
+    #[command(about = "Writes a catalog of all items on the controller as yaml")]
+    Discover {
+        #[arg(help = "File to write the catalog to, stdout if not set")]
+        output: Option<String>,
+        #[arg(
+            long,
+            env = "DISCOVER_ENTITY_NAME",
+            default_value = DEFAULT_ENTITY_NAME,
+            help = "Entity name of the discovered samples"
+        )]
+        entity_name: String,
+    },
+    #[command(about = "Checks every configured sample against the controller")]
+    Validate,
+    #[command(about = "Prints the raw value, parsed value and unit of a single item")]
+    Get {
+        #[arg(help = "Navigation path of the page, for example \"Informatie > Temperaturen\"")]
+        navigation: String,
+        #[arg(help = "Name of the item on the page, for example Aanvoer")]
+        item: String,
+        #[arg(long, help = "Prints the item as json")]
+        json: bool,
+    },
+    #[command(about = "Prints all items on a page")]
+    DumpPage {
+        #[arg(help = "Navigation path of the page, for example \"Informatie > Temperaturen\"")]
+        navigation: String,
+    },
+}
+
+#[derive(Args, Debug, Clone, Default, PartialEq)]
+pub struct RunArgs {
+    #[arg(
+        long,
+        help = "Prints the measurement instead of publishing it to nats and storing state"
+    )]
+    pub dry_run: bool,
+    #[arg(
+        long,
+        requires = "dry_run",
+        help = "Prints the measurement as json instead of a table"
+    )]
+    pub json: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct WebsocketArgs {
+    #[arg(
+        long = "host",
+        global = true,
+        env = "WEBSOCKET_HOST_IP",
+        default_value = "127.0.0.1",
+        help = "Address of the controller"
+    )]
+    pub host_address: String,
+    #[arg(
+        long = "port",
+        global = true,
+        env = "WEBSOCKET_HOST_PORT",
+        default_value_t = 8214,
+        help = "Websocket port of the controller"
+    )]
+    pub host_port: u32,
+    #[arg(
+        long,
+        global = true,
+        env = "WEBSOCKET_LOGIN_CODE",
+        hide_env_values = true,
+        help = "Login code of the controller's web interface"
+    )]
+    pub login_code: Option<String>,
+    #[arg(
+        long,
+        global = true,
+        env = "WEBSOCKET_CONNECT_TIMEOUT_SECONDS",
+        help = "Seconds to wait for a connection [default: 10]"
+    )]
+    pub connect_timeout_seconds: Option<u64>,
+    #[arg(
+        long,
+        global = true,
+        env = "WEBSOCKET_RESPONSE_TIMEOUT_SECONDS",
+        help = "Seconds to wait for each response [default: 10]"
+    )]
+    pub response_timeout_seconds: Option<u64>,
+    #[arg(
+        long,
+        global = true,
+        env = "WEBSOCKET_TOTAL_TIMEOUT_SECONDS",
+        help = "Seconds a scrape may take including retries [default: 180]"
+    )]
+    pub total_timeout_seconds: Option<u64>,
+    #[arg(
+        long,
+        global = true,
+        env = "WEBSOCKET_MAX_RETRIES",
+        default_value_t = 3,
+        help = "Retries of a scrape after a connection error"
+    )]
+    pub max_retries: u32,
+}
+
+impl WebsocketArgs {
+    pub fn config(&self) -> Result<WebsocketClientConfig, Box<dyn Error>> {
+        let login_code = match &self.login_code {
+            Some(login_code) => login_code.clone(),
+            None => {
+                return Err(Box::<dyn Error>::from(
+                    "Set the login code with --login-code or WEBSOCKET_LOGIN_CODE".to_string(),
+                ))
+            }
+        };
+
+        let defaults = Timeouts::default();
+        let timeouts = Timeouts {
+            connect: self
+                .connect_timeout_seconds
+                .map_or(defaults.connect, Duration::from_secs),
+            response: self
+                .response_timeout_seconds
+                .map_or(defaults.response, Duration::from_secs),
+            total: self
+                .total_timeout_seconds
+                .map_or(defaults.total, Duration::from_secs),
+        };
+
+        WebsocketClientConfig::new(
+            self.host_address.clone(),
+            self.host_port,
+            login_code,
+            timeouts,
+            self.max_retries,
+        )
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct TcpArgs {
+    #[arg(
+        long = "tcp-host",
+        global = true,
+        env = "TCP_HOST_IP",
+        default_value = "127.0.0.1",
+        help = "Address of the controller for the tcp transport"
+    )]
+    pub tcp_host_address: String,
+    #[arg(
+        long = "tcp-port",
+        global = true,
+        env = "TCP_HOST_PORT",
+        default_value_t = 8889,
+        help = "Port of the controller for the tcp transport"
+    )]
+    pub tcp_host_port: u32,
+}
+
+impl TcpArgs {
+    pub fn config(&self) -> Result<TcpClientConfig, Box<dyn Error>> {
+        TcpClientConfig::new(self.tcp_host_address.clone(), self.tcp_host_port)
+    }
+}
+
+fn parse_transport(value: &str) -> Result<Transport, String> {
+    value.parse().map_err(|e: Box<dyn Error>| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn cli_is_consistent() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn parse_get_with_flags_after_subcommand() {
+        // act
+        let cli = Cli::try_parse_from([
+            "jarvis-alpha-innotec-exporter",
+            "get",
+            "Informatie > Temperaturen",
+            "Aanvoer",
+            "--host",
+            "192.168.178.94",
+            "--login-code",
+            "999999",
+        ])
+        .unwrap();
+
+        assert_eq!(
+            cli.command_or_default(),
+            Command::Get {
+                navigation: "Informatie > Temperaturen".to_string(),
+                item: "Aanvoer".to_string(),
+                json: false,
+            }
+        );
+        assert_eq!(cli.websocket.host_address, "192.168.178.94".to_string());
+        assert_eq!(cli.websocket.login_code, Some("999999".to_string()));
+    }
+
+    #[test]
+    fn parse_defaults_to_run_without_subcommand() {
+        // act
+        let cli = Cli::try_parse_from(["jarvis-alpha-innotec-exporter"]).unwrap();
+
+        assert_eq!(cli.command_or_default(), Command::Run(RunArgs::default()));
+    }
+
+    #[test]
+    fn websocket_config_fails_for_tcp_transport() {
+        let cli = Cli::try_parse_from([
+            "jarvis-alpha-innotec-exporter",
+            "validate",
+            "--transport",
+            "tcp",
+            "--login-code",
+            "999999",
+        ])
+        .unwrap();
+
+        // act
+        let result = cli.websocket_config();
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,496 @@
+use crate::language::{name_matches, Language};
+use crate::model::ConfigSample;
+use crate::unit::Unit;
+use crate::value::{parse_duration, parse_number, ValueMapping};
+use serde::Deserialize;
+use serde_xml_rs::from_str;
+use std::error::Error;
+
+// <Content><item id='0x4816ac'><name>Aanvoer</name><value>22.0°C</value></item>...<name>Temperaturen</name></Content>
+#[derive(Debug, Deserialize)]
+struct RawContentPage {
+    #[serde(rename = "item", default)]
+    items: Vec<RawContentItem>, // `xml:"item"`
+    #[serde(default)]
+    name: String, // `xml:"name"`
+}
+
+#[derive(Debug, Deserialize)]
+struct RawContentItem {
+    id: String,   // `xml:"id,attr"`
+    name: String, // `xml:"name"`
+    #[serde(default)]
+    value: String, // `xml:"value"`
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContentPage {
+    pub name: String,
+    pub items: Vec<ContentItem>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContentItem {
+    pub id: String,
+    pub name: String,
+    // the value as the controller shows it, including its unit
+    pub text: String,
+    pub raw_value: String,
+    pub unit: Unit,
+}
+
+impl ContentPage {
+    pub fn from_response(response_message: &str) -> Result<Self, Box<dyn Error>> {
+        let raw_page: RawContentPage = from_str(response_message)?;
+
+        Ok(Self {
+            name: raw_page.name,
+            items: raw_page
+                .items
+                .into_iter()
+                .map(|raw_item| {
+                    let (raw_value, unit) = split_value_and_unit(&raw_item.value);
+                    // durations are converted to seconds regardless of the unit they're shown in
+                    let unit = if parse_duration(&raw_value).is_some() {
+                        Unit::Second
+                    } else {
+                        Unit::from(unit)
+                    };
+
+                    ContentItem {
+                        id: raw_item.id,
+                        name: raw_item.name,
+                        text: raw_item.value,
+                        raw_value,
+                        unit,
+                    }
+                })
+                .collect(),
+        })
+    }
+
+    pub fn select_item(
+        &self,
+        sample_config: &ConfigSample,
+        language: Option<Language>,
+    ) -> Result<&ContentItem, Box<dyn Error>> {
+        if let Some(item_id) = &sample_config.item_id {
+            return match self.items.iter().find(|item| &item.id == item_id) {
+                Some(item) => Ok(item),
+                None => Err(Box::<dyn Error>::from(format!(
+                    "No match for item with id {}",
+                    item_id
+                ))),
+            };
+        }
+
+        if sample_config.item_occurrence.is_none() && sample_config.item_unit.is_none() {
+            return self.get_item(&sample_config.item, language);
+        }
+
+        let candidates = self.get_candidates(sample_config, language);
+
+        let item = match sample_config.item_occurrence {
+            Some(occurrence) => candidates.get(occurrence).copied(),
+            None => candidates.first().copied(),
+        };
+
+        match item {
+            Some(item) => Ok(item),
+            None => Err(Box::<dyn Error>::from(format!(
+                "No match for item {} with occurrence {:?} and unit {:?}",
+                sample_config.item, sample_config.item_occurrence, sample_config.item_unit
+            ))),
+        }
+    }
+
+    pub fn is_ambiguous(&self, sample_config: &ConfigSample, language: Option<Language>) -> bool {
+        sample_config.item_id.is_none()
+            && sample_config.item_occurrence.is_none()
+            && self.get_candidates(sample_config, language).len() > 1
+    }
+
+    fn get_candidates(
+        &self,
+        sample_config: &ConfigSample,
+        language: Option<Language>,
+    ) -> Vec<&ContentItem> {
+        self.items
+            .iter()
+            .filter(|item| name_matches(&sample_config.item, &item.name, language))
+            .filter(|item| match &sample_config.item_unit {
+                Some(unit) => &item.unit == unit,
+                None => true,
+            })
+            .collect()
+    }
+
+    pub fn get_item(
+        &self,
+        name: &str,
+        language: Option<Language>,
+    ) -> Result<&ContentItem, Box<dyn Error>> {
+        let mut named_items = self
+            .items
+            .iter()
+            .filter(|item| name_matches(name, &item.name, language));
+
+        // a page can list a state and a measurement under the same name, prefer the measurement
+        let item = named_items
+            .clone()
+            .find(|item| item.is_placeholder() || item.value(None, None).is_ok())
+            .or_else(|| named_items.next());
+
+        match item {
+            Some(item) => Ok(item),
+            None => Err(Box::<dyn Error>::from(format!(
+                "No match for item {}",
+                name
+            ))),
+        }
+    }
+}
+
+impl ContentItem {
+    // the controller shows --- for sensors that aren't present or have no value available
+    pub fn is_placeholder(&self) -> bool {
+        self.raw_value == "---"
+    }
+
+    pub fn value(
+        &self,
+        value_mapping: Option<&ValueMapping>,
+        language: Option<Language>,
+    ) -> Result<f64, Box<dyn Error>> {
+        if self.is_placeholder() {
+            return Ok(0.0);
+        }
+
+        if let Some(value) = value_mapping.and_then(|vm| vm.map(&self.raw_value)) {
+            return Ok(value);
+        }
+
+        if let Some(seconds) = parse_duration(&self.raw_value) {
+            return Ok(seconds);
+        }
+
+        match parse_number(&self.raw_value, language) {
+            Some(value) => Ok(value),
+            None => Err(Box::<dyn Error>::from(format!(
+                "Value {} for item {} is not numeric and has no value mapping",
+                self.raw_value, self.name
+            ))),
+        }
+    }
+}
+
+fn split_value_and_unit(value: &str) -> (String, &str) {
+    let value = value.trim();
+
+    let chars: Vec<(usize, char)> = value.char_indices().collect();
+    let split_at = chars
+        .iter()
+        .enumerate()
+        .find(|(i, (_, c))| {
+            let numeric = c.is_ascii_digit() || *c == '-' || *c == '.' || *c == ',' || *c == ':';
+            // spaces only belong to the number when they group thousands, as in 12 345,6 kWh
+            let grouping = is_space(*c)
+                && *i > 0
+                && chars[*i - 1].1.is_ascii_digit()
+                && matches!(chars.get(*i + 1), Some((_, n)) if n.is_ascii_digit());
+            !numeric && !grouping
+        })
+        .map_or(value.len(), |(_, (byte_index, _))| *byte_index);
+
+    if split_at == 0 {
+        // text values such as Aan or Uit don't carry a unit
+        return (value.to_string(), "");
+    }
+
+    (value[..split_at].to_string(), value[split_at..].trim())
+}
+
+fn is_space(c: char) -> bool {
+    c == ' ' || c == '\u{a0}' || c == '\u{202f}'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jarvis_lib::model::SampleType;
+
+    const INGANGEN_RESPONSE: &str = "<Content><item id='0x4e7944'><name>ASD</name><value>Aan</value></item><item id='0x4ffbfc'><name>EVU</name><value>Aan</value></item><item id='0x4ef3b4'><name>HD</name><value>Uit</value></item><item id='0x4dac64'><name>MOT</name><value>Aan</value></item><item id='0x4ca4c4'><name>SWT</name><value>Uit</value></item><item id='0x4fa864'><name>Analoog-In 21</name><value>0.00 V</value></item><item id='0x4d5f1c'><name>Analoog-In 22</name><value>0.00 V</value></item><item id='0x4e6a3c'><name>HD</name><value>8.10 bar</value></item><item id='0x4ca47c'><name>ND</name><value>8.38 bar</value></item><item id='0x4e8004'><name>Debiet</name><value>1200 l/h</value></item><name>Ingangen</name></Content>";
+
+    fn sample_config(item: &str) -> ConfigSample {
+        ConfigSample::for_test(
+            SampleType::Pressure,
+            "Hogedruk",
+            "Informatie > Ingangen",
+            item,
+        )
+    }
+
+    #[test]
+    fn from_response_deserializes_content_xml() {
+        let response_message = "<Content><item id='0x4816ac'><name>Aanvoer</name><value>22.3°C</value></item><item id='0x44fdcc'><name>Retour</name><value>22.0°C</value></item><item id='0x4807dc'><name>Retour berekend</name><value>23.0°C</value></item><item id='0x45e1bc'><name>Heetgas</name><value>38.0°C</value></item><item id='0x448894'><name>Buitentemperatuur</name><value>11.6°C</value></item><item id='0x48047c'><name>Gemiddelde temp.</name><value>13.1°C</value></item><item id='0x457724'><name>Tapwater gemeten</name><value>54.2°C</value></item><item id='0x45e97c'><name>Tapwater ingesteld</name><value>57.0°C</value></item><item id='0x45a41c'><name>Bron-in</name><value>10.5°C</value></item><item id='0x480204'><name>Bron-uit</name><value>10.3°C</value></item><item id='0x4803cc'><name>Menggroep2-aanvoer</name><value>22.0°C</value></item><item id='0x4609cc'><name>Menggr2-aanv.ingest.</name><value>19.0°C</value></item><item id='0x45a514'><name>Zonnecollector</name><value>5.0°C</value></item><item id='0x461ecc'><name>Zonneboiler</name><value>150.0°C</value></item><item id='0x4817a4'><name>Externe energiebron</name><value>5.0°C</value></item><item id='0x4646b4'><name>Aanvoer max.</name><value>66.0°C</value></item><item id='0x45e76c'><name>Zuiggasleiding comp.</name><value>19.4°C</value></item><item id='0x4607d4'><name>Comp. verwarming</name><value>37.7°C</value></item><item id='0x43e60c'><name>Oververhitting</name><value>4.8 K</value></item><name>Temperaturen</name></Content>";
+
+        // act
+        let page = ContentPage::from_response(response_message).unwrap();
+
+        assert_eq!(page.name, "Temperaturen".to_string());
+        assert_eq!(page.items.len(), 19);
+        assert_eq!(
+            page.items[0],
+            ContentItem {
+                id: "0x4816ac".to_string(),
+                name: "Aanvoer".to_string(),
+                text: "22.3°C".to_string(),
+                raw_value: "22.3".to_string(),
+                unit: Unit::DegreesCelsius,
+            }
+        );
+        assert_eq!(page.items[18].raw_value, "4.8".to_string());
+        assert_eq!(page.items[18].unit, Unit::Kelvin);
+    }
+
+    #[test]
+    fn get_item_returns_value_for_item_without_unit() {
+        let response_message = "<Content><item id='0x4816ac'><name>Aanvoer</name><value>22.3°C</value></item><item id='0x44fdcc'><name>Retour</name><value>22.0°C</value></item><item id='0x4807dc'><name>Retour berekend</name><value>23.0°C</value></item><item id='0x45e1bc'><name>Heetgas</name><value>38.0°C</value></item><item id='0x448894'><name>Buitentemperatuur</name><value>11.6°C</value></item><item id='0x48047c'><name>Gemiddelde temp.</name><value>13.1°C</value></item><item id='0x457724'><name>Tapwater gemeten</name><value>54.2°C</value></item><item id='0x45e97c'><name>Tapwater ingesteld</name><value>57.0°C</value></item><item id='0x45a41c'><name>Bron-in</name><value>10.5°C</value></item><item id='0x480204'><name>Bron-uit</name><value>10.3°C</value></item><item id='0x4803cc'><name>Menggroep2-aanvoer</name><value>22.0°C</value></item><item id='0x4609cc'><name>Menggr2-aanv.ingest.</name><value>19.0°C</value></item><item id='0x45a514'><name>Zonnecollector</name><value>5.0°C</value></item><item id='0x461ecc'><name>Zonneboiler</name><value>150.0°C</value></item><item id='0x4817a4'><name>Externe energiebron</name><value>5.0°C</value></item><item id='0x4646b4'><name>Aanvoer max.</name><value>66.0°C</value></item><item id='0x45e76c'><name>Zuiggasleiding comp.</name><value>19.4°C</value></item><item id='0x4607d4'><name>Comp. verwarming</name><value>37.7°C</value></item><item id='0x43e60c'><name>Oververhitting</name><value>4.8 K</value></item><name>Temperaturen</name></Content>";
+        let page = ContentPage::from_response(response_message).unwrap();
+
+        // act
+        let value = page
+            .get_item("Aanvoer", None)
+            .unwrap()
+            .value(None, None)
+            .unwrap();
+
+        assert_eq!(value, 22.3);
+    }
+
+    #[test]
+    fn get_item_returns_value_for_item_with_regex_characters_in_name() {
+        let response_message = "<Content><item id='0x48047c'><name>Gemiddelde temp.</name><value>13.1°C</value></item><item id='0x4646b4'><name>Aanvoer max.</name><value>66.0°C</value></item><item id='0x45c200'><name>Dagen (Ma, Di,...)</name><value>2</value></item><item id='0x45df00'><name>5+2</name><value>1</value></item><name>Temperaturen</name></Content>";
+        let page = ContentPage::from_response(response_message).unwrap();
+
+        // act
+        let average = page
+            .get_item("Gemiddelde temp.", None)
+            .unwrap()
+            .value(None, None)
+            .unwrap();
+        let days = page
+            .get_item("Dagen (Ma, Di,...)", None)
+            .unwrap()
+            .value(None, None)
+            .unwrap();
+        let five_plus_two = page
+            .get_item("5+2", None)
+            .unwrap()
+            .value(None, None)
+            .unwrap();
+
+        assert_eq!(average, 13.1);
+        assert_eq!(days, 2.0);
+        assert_eq!(five_plus_two, 1.0);
+    }
+
+    #[test]
+    fn get_item_returns_error_if_item_id_is_not_in_response() {
+        let response_message = "<Content><item id='0x4816ac'><name>Aanvoer</name><value>22.3°C</value></item><item id='0x44fdcc'><name>Retour</name><value>22.0°C</value></item><name>Temperaturen</name></Content>";
+        let page = ContentPage::from_response(response_message).unwrap();
+
+        // act
+        let result = page.get_item("DoesNotExist", None);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn get_item_returns_value_for_item_with_pressure_unit() {
+        let page = ContentPage::from_response(INGANGEN_RESPONSE).unwrap();
+
+        // act
+        let item = page.get_item("HD", None).unwrap();
+
+        assert_eq!(item.value(None, None).unwrap(), 8.10);
+        assert_eq!(item.unit, Unit::Bar);
+    }
+
+    #[test]
+    fn value_returns_zero_for_placeholder() {
+        let item = ContentItem {
+            id: "0x45a514".to_string(),
+            name: "Zonnecollector".to_string(),
+            text: "---".to_string(),
+            raw_value: "---".to_string(),
+            unit: Unit::None,
+        };
+
+        assert_eq!(item.value(None, None).unwrap(), 0.0);
+    }
+
+    #[test]
+    fn select_item_returns_item_by_id() {
+        let page = ContentPage::from_response(INGANGEN_RESPONSE).unwrap();
+        let mut sample_config = sample_config("HD");
+        sample_config.item_id = Some("0x4ef3b4".to_string());
+
+        // act
+        let item = page.select_item(&sample_config, None).unwrap();
+
+        assert_eq!(item.raw_value, "Uit".to_string());
+    }
+
+    #[test]
+    fn select_item_returns_item_by_occurrence() {
+        let page = ContentPage::from_response(INGANGEN_RESPONSE).unwrap();
+        let mut sample_config = sample_config("HD");
+        sample_config.item_occurrence = Some(1);
+
+        // act
+        let item = page.select_item(&sample_config, None).unwrap();
+
+        assert_eq!(item.id, "0x4e6a3c".to_string());
+    }
+
+    #[test]
+    fn select_item_returns_item_by_unit() {
+        let page = ContentPage::from_response(INGANGEN_RESPONSE).unwrap();
+        let mut sample_config = sample_config("HD");
+        sample_config.item_unit = Some(Unit::Bar);
+
+        // act
+        let item = page.select_item(&sample_config, None).unwrap();
+
+        assert_eq!(item.id, "0x4e6a3c".to_string());
+    }
+
+    #[test]
+    fn select_item_returns_error_if_occurrence_is_out_of_range() {
+        let page = ContentPage::from_response(INGANGEN_RESPONSE).unwrap();
+        let mut sample_config = sample_config("HD");
+        sample_config.item_occurrence = Some(2);
+
+        // act
+        let result = page.select_item(&sample_config, None);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn is_ambiguous_returns_true_for_duplicate_name_without_selector() {
+        let page = ContentPage::from_response(INGANGEN_RESPONSE).unwrap();
+        let mut disambiguated_sample_config = sample_config("HD");
+        disambiguated_sample_config.item_unit = Some(Unit::Bar);
+
+        assert!(page.is_ambiguous(&sample_config("HD"), None));
+        assert!(!page.is_ambiguous(&disambiguated_sample_config, None));
+        assert!(!page.is_ambiguous(&sample_config("ND"), None));
+    }
+
+    #[test]
+    fn value_returns_mapped_value_for_state_text() {
+        let page = ContentPage::from_response(INGANGEN_RESPONSE).unwrap();
+        let value_mapping = ValueMapping::Builtin(crate::value::BuiltinValueMapping::OnOff);
+
+        // act
+        let evu = page
+            .get_item("EVU", None)
+            .unwrap()
+            .value(Some(&value_mapping), None)
+            .unwrap();
+        let swt = page
+            .get_item("SWT", None)
+            .unwrap()
+            .value(Some(&value_mapping), None)
+            .unwrap();
+
+        assert_eq!(evu, 1.0);
+        assert_eq!(swt, 0.0);
+    }
+
+    #[test]
+    fn value_returns_error_for_state_text_without_value_mapping() {
+        let page = ContentPage::from_response(INGANGEN_RESPONSE).unwrap();
+
+        // act
+        let result = page.get_item("EVU", None).unwrap().value(None, None);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn value_returns_seconds_for_operating_hours() {
+        let response_message = "<Content><item id='0x4ad1c4'><name>Bedrijfsuren VD1</name><value>12345h</value></item><item id='0x4c9c34'><name>Gem. looptijd VD1</name><value>1234:56</value></item><item id='0x4aa66c'><name>WP Sinds</name><value>00:12:34</value></item><name>Bedrijfsuren</name></Content>";
+        let page = ContentPage::from_response(response_message).unwrap();
+
+        // act
+        let compressor = page.get_item("Bedrijfsuren VD1", None).unwrap();
+        let average = page.get_item("Gem. looptijd VD1", None).unwrap();
+        let running = page.get_item("WP Sinds", None).unwrap();
+
+        assert_eq!(compressor.unit, Unit::Hour);
+        assert_eq!(compressor.value(None, None).unwrap(), 12345.0);
+        assert_eq!(average.unit, Unit::Second);
+        assert_eq!(average.value(None, None).unwrap(), 4445760.0);
+        assert_eq!(running.unit, Unit::Second);
+        assert_eq!(running.value(None, None).unwrap(), 754.0);
+    }
+
+    #[test]
+    fn value_parses_decimal_comma_for_german_and_french_pages() {
+        let german_page = ContentPage::from_response("<Content><item id='0x4816ac'><name>Vorlauf</name><value>22,3°C</value></item><item id='0x4a8b2c'><name>Heizung</name><value>12.345,6 kWh</value></item><name>Temperaturen</name></Content>").unwrap();
+        let french_page = ContentPage::from_response("<Content><item id='0x4816ac'><name>Départ</name><value>22,3°C</value></item><item id='0x4a8b2c'><name>Chauffage</name><value>12 345,6 kWh</value></item><name>Températures</name></Content>").unwrap();
+
+        // act
+        let german_flow = german_page.get_item("Vorlauf", None).unwrap();
+        let german_energy = german_page.get_item("Heizung", None).unwrap();
+        let french_flow = french_page.get_item("Départ", None).unwrap();
+        let french_energy = french_page.get_item("Chauffage", None).unwrap();
+
+        assert_eq!(german_flow.value(None, Some(Language::De)).unwrap(), 22.3);
+        assert_eq!(
+            german_energy.value(None, Some(Language::De)).unwrap(),
+            12345.6
+        );
+        assert_eq!(german_energy.unit, Unit::KilowattHour);
+        assert_eq!(french_flow.value(None, Some(Language::Fr)).unwrap(), 22.3);
+        assert_eq!(
+            french_energy.value(None, Some(Language::Fr)).unwrap(),
+            12345.6
+        );
+        assert_eq!(french_energy.unit, Unit::KilowattHour);
+    }
+
+    #[test]
+    fn value_parses_decimal_point_for_english_and_dutch_pages() {
+        let english_page = ContentPage::from_response("<Content><item id='0x4816ac'><name>Flow</name><value>22.3°C</value></item><item id='0x4a8b2c'><name>Heating</name><value>12,345.6 kWh</value></item><name>Temperatures</name></Content>").unwrap();
+        let dutch_page = ContentPage::from_response("<Content><item id='0x4a8b2c'><name>Verwarmen</name><value>12345.6 kWh</value></item><name>Energie</name></Content>").unwrap();
+
+        // act
+        let english_flow = english_page.get_item("Flow", None).unwrap();
+        let english_energy = english_page.get_item("Heating", None).unwrap();
+        let dutch_energy = dutch_page.get_item("Verwarmen", None).unwrap();
+
+        assert_eq!(english_flow.value(None, Some(Language::En)).unwrap(), 22.3);
+        assert_eq!(
+            english_energy.value(None, Some(Language::En)).unwrap(),
+            12345.6
+        );
+        assert_eq!(
+            dutch_energy.value(None, Some(Language::Nl)).unwrap(),
+            12345.6
+        );
+    }
+
+    #[test]
+    fn get_item_returns_item_by_canonical_english_name() {
+        let page = ContentPage::from_response("<Content><item id='0x4816ac'><name>Vorlauf</name><value>22,3°C</value></item><item id='0x44fdcc'><name>Rücklauf</name><value>22,0°C</value></item><name>Temperaturen</name></Content>").unwrap();
+
+        // act
+        let item = page.get_item("Return", Some(Language::De)).unwrap();
+
+        assert_eq!(item.id, "0x44fdcc".to_string());
+    }
+}
@@ -0,0 +1,192 @@
+use crate::content::ContentPage;
+use crate::language::{name_matches, Language};
+use crate::model::{ConfigSample, MissingValuePolicy};
+use crate::unit::Unit;
+use crate::value::{BuiltinValueMapping, ValueMapping};
+use jarvis_lib::model::{EntityType, MetricType, SampleType};
+use serde::Serialize;
+
+pub const DEFAULT_ENTITY_NAME: &str = "Alpha Innotec";
+
+// catalog of every item on every page, shaped like the config so entries can be pasted into sampleConfigs
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Catalog {
+    pub sample_configs: Vec<DiscoveredSample>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveredSample {
+    #[serde(flatten)]
+    pub sample_config: ConfigSample,
+    // ignored when reading the config, only there to help pick the samples worth exporting
+    pub discovered: DiscoveredItem,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveredItem {
+    pub id: String,
+    pub raw_value: String,
+    pub unit: Unit,
+}
+
+pub fn discover_samples(
+    navigation: &str,
+    page: &ContentPage,
+    entity_name: &str,
+    language: Option<Language>,
+) -> Vec<DiscoveredSample> {
+    let page_name = navigation.rsplit(" > ").next().unwrap_or(navigation);
+    let on_off = ValueMapping::Builtin(BuiltinValueMapping::OnOff);
+
+    page.items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let sample_type = item.unit.sample_type();
+            let metric_type = match sample_type {
+                SampleType::Energy => MetricType::Counter,
+                SampleType::Time if name_matches("Operating hours", page_name, language) => {
+                    MetricType::Counter
+                }
+                _ => MetricType::Gauge,
+            };
+
+            // items sharing a name on the page get selected by their occurrence
+            let same_name: Vec<usize> = page
+                .items
+                .iter()
+                .enumerate()
+                .filter(|(_, other)| other.name == item.name)
+                .map(|(j, _)| j)
+                .collect();
+            let item_occurrence = if same_name.len() > 1 {
+                same_name.iter().position(|j| *j == i)
+            } else {
+                None
+            };
+
+            let value_mapping = if on_off.map(&item.raw_value).is_some() {
+                Some(on_off.clone())
+            } else {
+                None
+            };
+
+            DiscoveredSample {
+                sample_config: ConfigSample {
+                    entity_type: EntityType::Device,
+                    entity_name: entity_name.to_string(),
+                    sample_type,
+                    sample_name: item.name.clone(),
+                    metric_type,
+                    value_multiplier: 1.0,
+                    navigation: navigation.to_string(),
+                    item: item.name.clone(),
+                    item_id: None,
+                    item_occurrence,
+                    item_unit: None,
+                    value_mapping,
+                    missing_value: MissingValuePolicy::Zero,
+                    required: false,
+                    calculation: None,
+                    parameter: None,
+                    visibility: None,
+                },
+                discovered: DiscoveredItem {
+                    id: item.id.clone(),
+                    raw_value: item.raw_value.clone(),
+                    unit: item.unit.clone(),
+                },
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INGANGEN_RESPONSE: &str = "<Content><item id='0x4e7944'><name>ASD</name><value>Aan</value></item><item id='0x4ef3b4'><name>HD</name><value>Uit</value></item><item id='0x4e6a3c'><name>HD</name><value>8.10 bar</value></item><item id='0x4e8004'><name>Debiet</name><value>1200 l/h</value></item><name>Ingangen</name></Content>";
+
+    #[test]
+    fn discover_samples_infers_sample_type_and_value_mapping() {
+        let page = ContentPage::from_response(INGANGEN_RESPONSE).unwrap();
+
+        // act
+        let samples = discover_samples(
+            "Informatie > Ingangen",
+            &page,
+            DEFAULT_ENTITY_NAME,
+            Some(Language::Nl),
+        );
+
+        assert_eq!(samples.len(), 4);
+        assert_eq!(
+            samples[0].sample_config.value_mapping,
+            Some(ValueMapping::Builtin(BuiltinValueMapping::OnOff))
+        );
+        assert_eq!(samples[0].sample_config.sample_type, SampleType::Invalid);
+        assert_eq!(samples[2].sample_config.sample_type, SampleType::Pressure);
+        assert_eq!(samples[2].sample_config.metric_type, MetricType::Gauge);
+        assert_eq!(samples[3].sample_config.sample_type, SampleType::Flow);
+        assert_eq!(samples[3].discovered.id, "0x4e8004".to_string());
+        assert_eq!(samples[3].discovered.raw_value, "1200".to_string());
+    }
+
+    #[test]
+    fn discover_samples_sets_occurrence_for_duplicate_names() {
+        let page = ContentPage::from_response(INGANGEN_RESPONSE).unwrap();
+
+        // act
+        let samples = discover_samples("Informatie > Ingangen", &page, DEFAULT_ENTITY_NAME, None);
+
+        assert_eq!(samples[0].sample_config.item_occurrence, None);
+        assert_eq!(samples[1].sample_config.item_occurrence, Some(0));
+        assert_eq!(samples[2].sample_config.item_occurrence, Some(1));
+    }
+
+    #[test]
+    fn discover_samples_marks_operating_hours_as_counters() {
+        let page = ContentPage::from_response("<Content><item id='0x4bd8e4'><name>Bedrijfsuren VD1</name><value>1234:56</value></item><name>Bedrijfsuren</name></Content>").unwrap();
+
+        // act
+        let samples = discover_samples(
+            "Informatie > Bedrijfsuren",
+            &page,
+            DEFAULT_ENTITY_NAME,
+            Some(Language::Nl),
+        );
+
+        assert_eq!(samples[0].sample_config.sample_type, SampleType::Time);
+        assert_eq!(samples[0].sample_config.metric_type, MetricType::Counter);
+    }
+
+    #[test]
+    fn serialize_catalog_to_config_yaml() {
+        let page = ContentPage::from_response(INGANGEN_RESPONSE).unwrap();
+        let catalog = Catalog {
+            sample_configs: discover_samples(
+                "Informatie > Ingangen",
+                &page,
+                DEFAULT_ENTITY_NAME,
+                None,
+            ),
+        };
+
+        // act
+        let yaml = serde_yaml::to_string(&catalog).unwrap();
+
+        assert!(yaml.contains("navigation: Informatie > Ingangen"));
+        assert!(yaml.contains("valueMapping: onOff"));
+        assert!(!yaml.contains("calculation"));
+        assert!(!yaml.contains("missingValue"));
+        let config: crate::model::Config = serde_yaml::from_str(&format!(
+            "location: My Home\nsanitizeSamples: false\n{}",
+            yaml
+        ))
+        .unwrap();
+        assert_eq!(config.sample_configs.len(), 4);
+    }
+}
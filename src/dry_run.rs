@@ -0,0 +1,70 @@
+use crate::metrics::snake_case;
+use crate::table;
+use jarvis_lib::model::Measurement;
+
+// one row per sample, for reading a scrape in a terminal
+pub fn render_table(measurements: &[Measurement]) -> String {
+    let mut rows = vec![vec![
+        "ENTITY".to_string(),
+        "SAMPLE".to_string(),
+        "SAMPLE TYPE".to_string(),
+        "METRIC TYPE".to_string(),
+        "VALUE".to_string(),
+    ]];
+    for sample in measurements.iter().flat_map(|m| m.samples.iter()) {
+        rows.push(vec![
+            sample.entity_name.clone(),
+            sample.sample_name.clone(),
+            snake_case(&format!("{:?}", sample.sample_type)),
+            snake_case(&format!("{:?}", sample.metric_type)),
+            sample.value.to_string(),
+        ]);
+    }
+
+    table::render(&rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use jarvis_lib::model::{EntityType, MetricType, Sample, SampleType};
+
+    #[test]
+    fn render_table_aligns_a_row_per_sample() {
+        let measurement = Measurement {
+            id: "cc6e17bb-fd60-4dde-acc3-0cda7d752ffe".to_string(),
+            source: "jarvis-alpha-innotec-exporter".to_string(),
+            location: "My Home".to_string(),
+            samples: vec![
+                Sample {
+                    entity_type: EntityType::Device,
+                    entity_name: "Alpha Innotec SWCV 92K3".to_string(),
+                    sample_type: SampleType::Temperature,
+                    sample_name: "Aanvoer".to_string(),
+                    metric_type: MetricType::Gauge,
+                    value: 32.1,
+                },
+                Sample {
+                    entity_type: EntityType::Device,
+                    entity_name: "Alpha Innotec SWCV 92K3".to_string(),
+                    sample_type: SampleType::Time,
+                    sample_name: "Bedrijfsuren compressor".to_string(),
+                    metric_type: MetricType::Counter,
+                    value: 36000000.0,
+                },
+            ],
+            measured_at_time: Utc::now(),
+        };
+
+        // act
+        let table = render_table(&[measurement]);
+
+        assert_eq!(
+            table,
+            "ENTITY                   SAMPLE                   SAMPLE TYPE  METRIC TYPE  VALUE\n\
+             Alpha Innotec SWCV 92K3  Aanvoer                  temperature  gauge        32.1\n\
+             Alpha Innotec SWCV 92K3  Bedrijfsuren compressor  time         counter      36000000\n"
+        );
+    }
+}
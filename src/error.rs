@@ -0,0 +1,133 @@
+use std::error::Error;
+use std::fmt;
+use tokio_tungstenite::tungstenite;
+
+#[derive(Debug)]
+pub enum LuxtronikError {
+    // the controller didn't connect or respond in time
+    Timeout(String),
+    // nothing listens on the configured address and port
+    ConnectionRefused(String),
+    // the controller rejected the login code or didn't respond with its navigation
+    LoginFailed(String),
+    // the controller closed the session, with the reason it gave if any
+    ConnectionClosed(Option<String>),
+    // websocket failures such as a dropped connection
+    Transport(Box<tungstenite::Error>),
+    // the controller sent something other than the expected response
+    Protocol(String),
+    // a response couldn't be parsed as navigation or page content
+    Parse(String),
+    // the configured navigation path doesn't exist on the controller
+    PageNotFound(String),
+    // the configured item doesn't exist on the page
+    ItemNotFound(String),
+    // the item exists but its value can't be exported as configured
+    InvalidValue(String),
+    // more samples failed than the config allows
+    TooManyFailedSamples(String),
+}
+
+impl LuxtronikError {
+    // whether reconnecting might succeed; configuration mistakes and a wrong login code stay wrong
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            LuxtronikError::Timeout(_)
+            | LuxtronikError::ConnectionRefused(_)
+            | LuxtronikError::ConnectionClosed(_)
+            | LuxtronikError::Protocol(_) => true,
+            LuxtronikError::Transport(e) => !matches!(
+                e.as_ref(),
+                tungstenite::Error::Url(_) | tungstenite::Error::Http(_)
+            ),
+            LuxtronikError::LoginFailed(_)
+            | LuxtronikError::Parse(_)
+            | LuxtronikError::PageNotFound(_)
+            | LuxtronikError::ItemNotFound(_)
+            | LuxtronikError::InvalidValue(_)
+            | LuxtronikError::TooManyFailedSamples(_) => false,
+        }
+    }
+}
+
+impl fmt::Display for LuxtronikError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LuxtronikError::Timeout(message) => write!(f, "Timeout: {}", message),
+            LuxtronikError::ConnectionRefused(message) => {
+                write!(f, "Connection refused: {}", message)
+            }
+            LuxtronikError::LoginFailed(message) => write!(f, "Login failed: {}", message),
+            LuxtronikError::ConnectionClosed(Some(reason)) => {
+                write!(f, "Connection closed by controller: {}", reason)
+            }
+            LuxtronikError::ConnectionClosed(None) => write!(f, "Connection closed by controller"),
+            LuxtronikError::Transport(e) => write!(f, "Websocket error: {}", e),
+            LuxtronikError::Protocol(message) => write!(f, "Protocol error: {}", message),
+            LuxtronikError::Parse(message) => write!(f, "Parse error: {}", message),
+            LuxtronikError::PageNotFound(page) => write!(f, "Page {} does not exist", page),
+            LuxtronikError::ItemNotFound(message)
+            | LuxtronikError::InvalidValue(message)
+            | LuxtronikError::TooManyFailedSamples(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl Error for LuxtronikError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LuxtronikError::Transport(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<tungstenite::Error> for LuxtronikError {
+    fn from(e: tungstenite::Error) -> Self {
+        LuxtronikError::Transport(Box::new(e))
+    }
+}
+
+impl From<serde_xml_rs::Error> for LuxtronikError {
+    fn from(e: serde_xml_rs::Error) -> Self {
+        LuxtronikError::Parse(e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_retryable_returns_false_for_login_failure() {
+        assert!(LuxtronikError::Timeout("reading response".to_string()).is_retryable());
+        assert!(LuxtronikError::ConnectionRefused("127.0.0.1:8214".to_string()).is_retryable());
+        assert!(!LuxtronikError::LoginFailed("unexpected response".to_string()).is_retryable());
+    }
+
+    #[test]
+    fn display_includes_close_reason_if_given() {
+        assert_eq!(
+            LuxtronikError::ConnectionClosed(Some("busy".to_string())).to_string(),
+            "Connection closed by controller: busy".to_string()
+        );
+        assert_eq!(
+            LuxtronikError::ConnectionClosed(None).to_string(),
+            "Connection closed by controller".to_string()
+        );
+    }
+
+    #[test]
+    fn is_retryable_returns_true_for_connection_errors_only() {
+        assert!(
+            LuxtronikError::Transport(Box::new(tungstenite::Error::ConnectionClosed))
+                .is_retryable()
+        );
+        assert!(
+            !LuxtronikError::PageNotFound("Informatie > Temperatuur".to_string()).is_retryable()
+        );
+        assert!(
+            !LuxtronikError::ItemNotFound("No match for item Aanvoer".to_string()).is_retryable()
+        );
+    }
+}
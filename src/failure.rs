@@ -0,0 +1,154 @@
+use crate::error::LuxtronikError;
+use crate::model::ConfigSample;
+use jarvis_lib::model::{EntityType, MetricType, Sample, SampleType};
+use std::fmt;
+use tracing::warn;
+
+pub const FAILED_SAMPLES_SAMPLE_NAME: &str = "Failed samples";
+
+// a configured sample that couldn't be read, kept so the rest of the measurement can still be published
+#[derive(Debug, Clone, PartialEq)]
+pub struct FailedSample {
+    pub sample_name: String,
+    pub navigation: String,
+    pub item: String,
+    pub reason: String,
+}
+
+impl FailedSample {
+    pub fn new(sample_config: &ConfigSample, reason: &dyn fmt::Display) -> Self {
+        Self {
+            sample_name: sample_config.sample_name.clone(),
+            navigation: sample_config.navigation.clone(),
+            item: sample_config.item.clone(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for FailedSample {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} ({} > {}): {}",
+            self.sample_name, self.navigation, self.item, self.reason
+        )
+    }
+}
+
+// records the samples that failed with the given error, unless one of them is required: those still fail the whole
+// run
+pub fn record_failed_samples<E: fmt::Display>(
+    failed_samples: &mut Vec<FailedSample>,
+    sample_configs: &[ConfigSample],
+    error: E,
+) -> Result<(), E> {
+    if sample_configs.iter().any(|sc| sc.required) {
+        return Err(error);
+    }
+
+    for sample_config in sample_configs.iter() {
+        failed_samples.push(FailedSample::new(sample_config, &error));
+    }
+
+    Ok(())
+}
+
+// logs the failed samples and fails the run when there are more than allowed; without a maximum the run only
+// fails when none of the samples could be read
+pub fn check_failed_samples(
+    failed_samples: &[FailedSample],
+    sample_count: usize,
+    max_failed_samples: Option<usize>,
+) -> Result<(), LuxtronikError> {
+    for failed_sample in failed_samples.iter() {
+        warn!(
+            sample_name = %failed_sample.sample_name,
+            navigation = %failed_sample.navigation,
+            item = %failed_sample.item,
+            reason = %failed_sample.reason,
+            "Failed reading sample {}",
+            failed_sample
+        );
+    }
+
+    let too_many = match max_failed_samples {
+        Some(max_failed_samples) => failed_samples.len() > max_failed_samples,
+        None => sample_count > 0 && failed_samples.len() == sample_count,
+    };
+
+    if too_many {
+        return Err(LuxtronikError::TooManyFailedSamples(format!(
+            "{} of {} samples failed: {}",
+            failed_samples.len(),
+            sample_count,
+            failed_samples
+                .iter()
+                .map(|fs| fs.to_string())
+                .collect::<Vec<String>>()
+                .join("; ")
+        )));
+    }
+
+    Ok(())
+}
+
+// self-monitoring sample with the number of samples that couldn't be read in this measurement
+pub fn failed_samples_sample(entity_name: &str, failed_samples: &[FailedSample]) -> Sample {
+    Sample {
+        entity_type: EntityType::Device,
+        entity_name: entity_name.to_string(),
+        sample_type: SampleType::Invalid,
+        sample_name: FAILED_SAMPLES_SAMPLE_NAME.to_string(),
+        metric_type: MetricType::Gauge,
+        value: failed_samples.len() as f64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failed_sample(sample_name: &str) -> FailedSample {
+        FailedSample {
+            sample_name: sample_name.to_string(),
+            navigation: "Informatie > Temperaturen".to_string(),
+            item: sample_name.to_string(),
+            reason: format!("No match for item {}", sample_name),
+        }
+    }
+
+    #[test]
+    fn check_failed_samples_fails_when_above_maximum() {
+        let failed_samples = vec![failed_sample("Aanvoer"), failed_sample("Retour")];
+
+        // act
+        let at_maximum = check_failed_samples(&failed_samples, 10, Some(2));
+        let above_maximum = check_failed_samples(&failed_samples, 10, Some(1));
+
+        assert!(at_maximum.is_ok());
+        assert!(above_maximum.is_err());
+    }
+
+    #[test]
+    fn check_failed_samples_without_maximum_fails_only_when_all_samples_failed() {
+        let failed_samples = vec![failed_sample("Aanvoer"), failed_sample("Retour")];
+
+        // act
+        let some_failed = check_failed_samples(&failed_samples, 3, None);
+        let all_failed = check_failed_samples(&failed_samples, 2, None);
+
+        assert!(some_failed.is_ok());
+        assert!(all_failed.is_err());
+        assert!(check_failed_samples(&[], 0, None).is_ok());
+    }
+
+    #[test]
+    fn failed_samples_sample_counts_failed_samples() {
+        // act
+        let sample = failed_samples_sample("Alpha Innotec SWCV 92K3", &[failed_sample("Aanvoer")]);
+
+        assert_eq!(sample.sample_name, FAILED_SAMPLES_SAMPLE_NAME.to_string());
+        assert_eq!(sample.value, 1.0);
+    }
+}
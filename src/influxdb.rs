@@ -0,0 +1,337 @@
+use crate::metrics::snake_case;
+use crate::model::Config;
+use crate::runtime;
+use jarvis_lib::measurement_client::MeasurementClient;
+use jarvis_lib::model::Measurement;
+use reqwest::StatusCode;
+use std::env;
+use std::error::Error;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::{debug, info, warn};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+// a hanging write would otherwise block the scrape, as the measurement client waits for it
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_ATTEMPTS: u32 = 3;
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+// lines kept for a later attempt while influxdb is unreachable, the oldest are dropped beyond this
+const MAX_PENDING_LINES: usize = 100_000;
+
+#[derive(Debug)]
+pub struct InfluxdbClientConfig {
+    write_url: String,
+    token: Option<String>,
+    batch_size: usize,
+}
+
+impl InfluxdbClientConfig {
+    pub fn new(
+        write_url: String,
+        token: Option<String>,
+        batch_size: usize,
+    ) -> Result<Self, Box<dyn Error>> {
+        if batch_size == 0 {
+            return Err(Box::<dyn Error>::from(
+                "InfluxDB batch size should be at least 1".to_string(),
+            ));
+        }
+
+        debug!(
+            "InfluxdbClientConfig::new(write_url: {}, batch_size: {})",
+            write_url, batch_size
+        );
+
+        Ok(Self {
+            write_url,
+            token,
+            batch_size,
+        })
+    }
+
+    pub fn from_env() -> Result<Option<Self>, Box<dyn Error>> {
+        let write_url = match env::var("INFLUXDB_WRITE_URL") {
+            Ok(write_url) if !write_url.is_empty() => write_url,
+            _ => return Ok(None),
+        };
+        let token = env::var("INFLUXDB_TOKEN").ok().filter(|t| !t.is_empty());
+        let batch_size: usize = env::var("INFLUXDB_BATCH_SIZE")
+            .unwrap_or_else(|_| "5000".to_string())
+            .parse()?;
+
+        Ok(Some(Self::new(write_url, token, batch_size)?))
+    }
+}
+
+pub struct InfluxdbClient {
+    config: InfluxdbClientConfig,
+    client: reqwest::Client,
+    pending_lines: Mutex<Vec<String>>,
+}
+
+impl InfluxdbClient {
+    pub fn new(config: InfluxdbClientConfig) -> Result<Self, Box<dyn Error>> {
+        let client = reqwest::Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(REQUEST_TIMEOUT)
+            .build()?;
+
+        Ok(Self {
+            config,
+            client,
+            pending_lines: Mutex::new(Vec::new()),
+        })
+    }
+
+    // lines left from earlier failures are written first
+    pub async fn write(&self, measurements: &[Measurement]) -> Result<(), Box<dyn Error>> {
+        let mut lines: Vec<String> = std::mem::take(&mut *self.pending_lines.lock().unwrap());
+        lines.extend(measurements.iter().flat_map(to_line_protocol));
+
+        let mut written = 0;
+        while written < lines.len() {
+            let batch_end = std::cmp::min(written + self.config.batch_size, lines.len());
+
+            if let Err(e) = self.write_batch(&lines[written..batch_end]).await {
+                let mut pending = lines.split_off(written);
+                if pending.len() > MAX_PENDING_LINES {
+                    warn!(
+                        "Dropping {} InfluxDB lines that couldn't be written",
+                        pending.len() - MAX_PENDING_LINES
+                    );
+                    pending.drain(..pending.len() - MAX_PENDING_LINES);
+                }
+                *self.pending_lines.lock().unwrap() = pending;
+
+                return Err(e);
+            }
+
+            written = batch_end;
+        }
+
+        info!("Wrote {} lines to InfluxDB", written);
+
+        Ok(())
+    }
+
+    async fn write_batch(&self, lines: &[String]) -> Result<(), Box<dyn Error>> {
+        let body = lines.join("\n");
+        let mut backoff = INITIAL_BACKOFF;
+        let mut attempt = 1;
+
+        loop {
+            let mut request = self.client.post(&self.config.write_url).body(body.clone());
+            if let Some(token) = &self.config.token {
+                request = request.header("Authorization", format!("Token {}", token));
+            }
+
+            let error = match request.send().await {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response) => {
+                    let status = response.status();
+                    let message = response.text().await.unwrap_or_default();
+                    let error = format!("InfluxDB responded with {}: {}", status, message);
+
+                    if is_rejected(status) {
+                        warn!("Dropping {} lines: {}", lines.len(), error);
+                        return Ok(());
+                    }
+                    // auth and not found errors keep the lines pending until the configuration is fixed
+                    if !is_retryable(status) {
+                        return Err(Box::<dyn Error>::from(error));
+                    }
+                    error
+                }
+                Err(e) => e.to_string(),
+            };
+
+            if attempt >= MAX_ATTEMPTS {
+                return Err(Box::<dyn Error>::from(error));
+            }
+
+            warn!(
+                "Attempt {} of {} writing to InfluxDB failed, retrying in {:?}: {}",
+                attempt, MAX_ATTEMPTS, backoff, error
+            );
+            sleep(backoff).await;
+            backoff *= 2;
+            attempt += 1;
+        }
+    }
+}
+
+// malformed lines stay rejected, so they're dropped instead of kept pending
+fn is_rejected(status: StatusCode) -> bool {
+    status == StatusCode::BAD_REQUEST || status == StatusCode::UNPROCESSABLE_ENTITY
+}
+
+fn is_retryable(status: StatusCode) -> bool {
+    status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+pub struct InfluxdbMeasurementClient {
+    measurement_client: Box<dyn MeasurementClient<Config>>,
+    influxdb_client: InfluxdbClient,
+}
+
+impl InfluxdbMeasurementClient {
+    pub fn new(
+        measurement_client: Box<dyn MeasurementClient<Config>>,
+        influxdb_client: InfluxdbClient,
+    ) -> Self {
+        Self {
+            measurement_client,
+            influxdb_client,
+        }
+    }
+}
+
+impl MeasurementClient<Config> for InfluxdbMeasurementClient {
+    fn get_measurements(
+        &self,
+        config: Config,
+        last_measurement: Option<Vec<Measurement>>,
+    ) -> Result<Vec<Measurement>, Box<dyn Error>> {
+        let measurements = self
+            .measurement_client
+            .get_measurements(config, last_measurement)?;
+
+        // lines that couldn't be written are retried with the next measurement
+        let result = runtime::block_on(self.influxdb_client.write(&measurements));
+        if let Err(e) = result {
+            warn!("Writing to InfluxDB failed: {}", e);
+        }
+
+        Ok(measurements)
+    }
+}
+
+pub fn to_line_protocol(measurement: &Measurement) -> Vec<String> {
+    let timestamp = measurement.measured_at_time.timestamp() * 1_000_000_000
+        + measurement.measured_at_time.timestamp_subsec_nanos() as i64;
+
+    measurement
+        .samples
+        .iter()
+        .map(|sample| {
+            let tags = [
+                (
+                    "entity_type",
+                    snake_case(&format!("{:?}", sample.entity_type)),
+                ),
+                ("entity_name", escape(&sample.entity_name, true)),
+                (
+                    "sample_type",
+                    snake_case(&format!("{:?}", sample.sample_type)),
+                ),
+                ("sample_name", escape(&sample.sample_name, true)),
+                (
+                    "metric_type",
+                    snake_case(&format!("{:?}", sample.metric_type)),
+                ),
+            ];
+
+            // influxdb doesn't accept empty tag values, so those tags are left out
+            format!(
+                "{}{} value={} {}",
+                escape(&measurement.location, false),
+                tags.iter()
+                    .filter(|(_, value)| !value.is_empty())
+                    .map(|(key, value)| format!(",{}={}", key, value))
+                    .collect::<String>(),
+                sample.value,
+                timestamp
+            )
+        })
+        .collect()
+}
+
+// measurement names escape commas and spaces, tag keys and values escape equal signs as well
+fn escape(value: &str, tag: bool) -> String {
+    let mut escaped = String::new();
+    for c in value.chars() {
+        if c == ',' || c == ' ' || (tag && c == '=') || c == '\\' {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use jarvis_lib::model::{EntityType, MetricType, Sample, SampleType};
+
+    #[test]
+    fn to_line_protocol_writes_a_line_per_sample() {
+        let measurement = Measurement {
+            id: "cc6e17bb-fd60-4dde-acc3-0cda7d752ffe".to_string(),
+            source: "jarvis-alpha-innotec-exporter".to_string(),
+            location: "My Home".to_string(),
+            samples: vec![Sample {
+                entity_type: EntityType::Device,
+                entity_name: "Alpha Innotec SWCV 92K3".to_string(),
+                sample_type: SampleType::Temperature,
+                sample_name: "Retour berekend".to_string(),
+                metric_type: MetricType::Gauge,
+                value: 28.4,
+            }],
+            measured_at_time: Utc.timestamp_opt(1_600_000_000, 500).unwrap(),
+        };
+
+        // act
+        let lines = to_line_protocol(&measurement);
+
+        assert_eq!(
+            lines,
+            vec!["My\\ Home,entity_type=device,entity_name=Alpha\\ Innotec\\ SWCV\\ 92K3,sample_type=temperature,sample_name=Retour\\ berekend,metric_type=gauge value=28.4 1600000000000000500".to_string()]
+        );
+    }
+
+    #[test]
+    fn to_line_protocol_leaves_out_empty_tags() {
+        let measurement = Measurement {
+            id: "cc6e17bb-fd60-4dde-acc3-0cda7d752ffe".to_string(),
+            source: "jarvis-alpha-innotec-exporter".to_string(),
+            location: "My Home".to_string(),
+            samples: vec![Sample {
+                entity_type: EntityType::Device,
+                entity_name: "".to_string(),
+                sample_type: SampleType::Temperature,
+                sample_name: "Aanvoer".to_string(),
+                metric_type: MetricType::Gauge,
+                value: 32.1,
+            }],
+            measured_at_time: Utc.timestamp_opt(1_600_000_000, 0).unwrap(),
+        };
+
+        // act
+        let lines = to_line_protocol(&measurement);
+
+        assert_eq!(
+            lines,
+            vec!["My\\ Home,entity_type=device,sample_type=temperature,sample_name=Aanvoer,metric_type=gauge value=32.1 1600000000000000000".to_string()]
+        );
+    }
+
+    #[test]
+    fn only_malformed_lines_are_rejected() {
+        assert!(is_rejected(StatusCode::BAD_REQUEST));
+        assert!(is_rejected(StatusCode::UNPROCESSABLE_ENTITY));
+        assert!(!is_rejected(StatusCode::UNAUTHORIZED));
+        assert!(!is_rejected(StatusCode::FORBIDDEN));
+        assert!(!is_rejected(StatusCode::NOT_FOUND));
+        assert!(!is_retryable(StatusCode::UNAUTHORIZED));
+        assert!(is_retryable(StatusCode::TOO_MANY_REQUESTS));
+        assert!(is_retryable(StatusCode::SERVICE_UNAVAILABLE));
+    }
+
+    #[test]
+    fn escape_escapes_equal_signs_in_tags_only() {
+        assert_eq!(escape("a=b,c", true), "a\\=b\\,c");
+        assert_eq!(escape("a=b,c", false), "a=b\\,c");
+    }
+}
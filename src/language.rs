@@ -0,0 +1,206 @@
+use serde::{Deserialize, Serialize};
+
+// canonical english page and item names, followed by the dutch, german and french names the controller shows;
+// names that aren't in this table can still be configured in the controller language
+const TRANSLATIONS: &[[&str; 4]] = &[
+    // navigation
+    ["Information", "Informatie", "Informationen", "Informations"],
+    [
+        "Temperatures",
+        "Temperaturen",
+        "Temperaturen",
+        "Températures",
+    ],
+    ["Inputs", "Ingangen", "Eingänge", "Entrées"],
+    ["Outputs", "Uitgangen", "Ausgänge", "Sorties"],
+    ["Timers", "Aflooptijden", "Ablaufzeiten", "Temporisations"],
+    [
+        "Operating hours",
+        "Bedrijfsuren",
+        "Betriebsstunden",
+        "Heures de fonctionnement",
+    ],
+    [
+        "Error memory",
+        "Storingsbuffer",
+        "Fehlerspeicher",
+        "Mémoire des défauts",
+    ],
+    ["Shutdowns", "Afschakelingen", "Abschaltungen", "Coupures"],
+    [
+        "System status",
+        "Installatiestatus",
+        "Anlagenstatus",
+        "État de l'installation",
+    ],
+    ["Energy", "Energie", "Wärmemenge", "Énergie"],
+    ["Settings", "Instelling", "Einstellungen", "Réglages"],
+    [
+        "Operating mode",
+        "Bedrijfsmode",
+        "Betriebsart",
+        "Mode de fonctionnement",
+    ],
+    [
+        "Clock program",
+        "Klokprogramma",
+        "Zeitprogramm",
+        "Programme horaire",
+    ],
+    ["Heating", "Verwarmen", "Heizung", "Chauffage"],
+    ["Hot water", "Warmwater", "Warmwasser", "Eau chaude"],
+    ["Pool", "Zwembad", "Schwimmbad", "Piscine"],
+    // items
+    ["Flow", "Aanvoer", "Vorlauf", "Départ"],
+    ["Return", "Retour", "Rücklauf", "Retour"],
+    [
+        "Return target",
+        "Retour berekend",
+        "Rückl.-Soll",
+        "Retour consigne",
+    ],
+    ["Hot gas", "Heetgas", "Heißgas", "Gaz chauds"],
+    [
+        "Outside temperature",
+        "Buitentemperatuur",
+        "Außentemperatur",
+        "Température extérieure",
+    ],
+    [
+        "Average temperature",
+        "Gemiddelde temp.",
+        "Mitteltemperatur",
+        "Temp. moyenne",
+    ],
+    [
+        "Hot water actual",
+        "Tapwater gemeten",
+        "Warmwasser-Ist",
+        "ECS réelle",
+    ],
+    [
+        "Hot water target",
+        "Tapwater ingesteld",
+        "Warmwasser-Soll",
+        "ECS consigne",
+    ],
+    [
+        "Heat source in",
+        "Bron-in",
+        "Wärmequelle-Ein",
+        "Source chaleur entrée",
+    ],
+    [
+        "Heat source out",
+        "Bron-uit",
+        "Wärmequelle-Aus",
+        "Source chaleur sortie",
+    ],
+    ["Superheat", "Oververhitting", "Überhitzung", "Surchauffe"],
+    ["Flow rate", "Debiet", "Durchfluss", "Débit"],
+];
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    Nl,
+    De,
+    En,
+    Fr,
+}
+
+impl Language {
+    fn column(&self) -> usize {
+        match self {
+            Language::En => 0,
+            Language::Nl => 1,
+            Language::De => 2,
+            Language::Fr => 3,
+        }
+    }
+
+    pub fn translate(&self, canonical_name: &str) -> Option<&'static str> {
+        TRANSLATIONS
+            .iter()
+            .find(|row| row[0] == canonical_name)
+            .map(|row| row[self.column()])
+    }
+
+    pub fn decimal_separator(&self) -> char {
+        match self {
+            Language::Nl | Language::En => '.',
+            Language::De | Language::Fr => ',',
+        }
+    }
+
+    pub fn thousands_separator(&self) -> Option<char> {
+        match self {
+            // the dutch firmware uses a decimal point and doesn't group thousands
+            Language::Nl => None,
+            Language::En => Some(','),
+            Language::De => Some('.'),
+            // french groups thousands with a (non-breaking) space, which is stripped anyway
+            Language::Fr => None,
+        }
+    }
+
+    // detects the controller language from the name of the first top level menu
+    pub fn from_menu_name(name: &str) -> Option<Self> {
+        match name {
+            "Informatie" => Some(Language::Nl),
+            "Informationen" => Some(Language::De),
+            "Information" => Some(Language::En),
+            "Informations" => Some(Language::Fr),
+            _ => None,
+        }
+    }
+}
+
+// checks whether a configured page or item name, either canonical english or in the controller language, refers to
+// the name shown by the controller; without a known language translations in any language are accepted
+pub fn name_matches(configured_name: &str, name: &str, language: Option<Language>) -> bool {
+    if configured_name == name {
+        return true;
+    }
+
+    TRANSLATIONS
+        .iter()
+        .filter(|row| row[0] == configured_name)
+        .any(|row| match language {
+            Some(language) => row[language.column()] == name,
+            None => row.contains(&name),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_menu_name_returns_language_for_information_menu() {
+        assert_eq!(Language::from_menu_name("Informatie"), Some(Language::Nl));
+        assert_eq!(
+            Language::from_menu_name("Informationen"),
+            Some(Language::De)
+        );
+        assert_eq!(Language::from_menu_name("Instelling"), None);
+    }
+
+    #[test]
+    fn translate_returns_name_in_controller_language() {
+        assert_eq!(Language::Nl.translate("Flow"), Some("Aanvoer"));
+        assert_eq!(Language::De.translate("Inputs"), Some("Eingänge"));
+        assert_eq!(Language::En.translate("Inputs"), Some("Inputs"));
+        assert_eq!(Language::Fr.translate("Unknown"), None);
+    }
+
+    #[test]
+    fn name_matches_accepts_canonical_and_localized_names() {
+        assert!(name_matches("Aanvoer", "Aanvoer", Some(Language::Nl)));
+        assert!(name_matches("Flow", "Aanvoer", Some(Language::Nl)));
+        assert!(name_matches("Flow", "Vorlauf", Some(Language::De)));
+        assert!(name_matches("Flow", "Vorlauf", None));
+        assert!(!name_matches("Flow", "Vorlauf", Some(Language::Nl)));
+        assert!(!name_matches("Return", "Aanvoer", None));
+    }
+}
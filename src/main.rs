@@ -1,11 +1,49 @@
+mod cli;
+mod content;
+mod discovery;
+mod dry_run;
+mod error;
+mod failure;
+mod influxdb;
+mod language;
+mod metrics;
 mod model;
+mod mqtt;
+mod navigation_cache;
+mod query;
+mod runtime;
+mod sanitizer;
+mod table;
+mod tcp_client;
+mod unit;
+mod validation;
+mod value;
 mod websocket_client;
 
+use clap::Parser;
+use cli::{Cli, Command, RunArgs};
+use discovery::Catalog;
+use influxdb::{InfluxdbClient, InfluxdbClientConfig, InfluxdbMeasurementClient};
 use jarvis_lib::config_client::{ConfigClient, ConfigClientConfig};
 use jarvis_lib::exporter_service::{ExporterService, ExporterServiceConfig};
+use jarvis_lib::measurement_client::MeasurementClient;
 use jarvis_lib::nats_client::{NatsClient, NatsClientConfig};
 use jarvis_lib::state_client::{StateClient, StateClientConfig};
-use websocket_client::{WebsocketClient, WebsocketClientConfig};
+use metrics::{LatestMeasurements, MetricsMeasurementClient, MetricsServer, MetricsServerConfig};
+use model::{Config, Transport};
+use mqtt::{MqttClient, MqttClientConfig, MqttMeasurementClient};
+use navigation_cache::{NavigationCacheClient, NavigationCacheClientConfig};
+use query::ItemValue;
+use std::fs;
+use std::sync::Arc;
+use std::time::Duration;
+use tcp_client::TcpClient;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::time;
+use tracing::{error, info, warn};
+use unit::SampleUnits;
+use validation::{suggest, SampleValidation};
+use websocket_client::{Sessions, WebsocketClient};
 
 #[tokio::main]
 pub async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -14,8 +52,77 @@ pub async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
         .init();
 
-    let websocket_client_config = WebsocketClientConfig::from_env()?;
-    let websocket_client = WebsocketClient::new(websocket_client_config);
+    let cli = Cli::parse();
+
+    let scrape_interval = match cli.command_or_default() {
+        Command::Run(RunArgs { dry_run: false, .. }) => None,
+        Command::Run(RunArgs {
+            dry_run: true,
+            json,
+        }) => return dry_run(&cli, json).await,
+        Command::Daemon {
+            scrape_interval_seconds,
+        } => Some(Duration::from_secs(scrape_interval_seconds)),
+        Command::Discover {
+            output,
+            entity_name,
+        } => return discover(&cli, output.as_ref(), &entity_name).await,
+        Command::Validate => return validate(&cli).await,
+        Command::Get {
+            navigation,
+            item,
+            json,
+        } => return get(&cli, &navigation, &item, json).await,
+        Command::DumpPage { navigation } => return dump_page(&cli, &navigation).await,
+    };
+
+    // daemon mode keeps the websocket sessions open between scrapes instead of logging in on every run
+    let sessions = scrape_interval.map(|_| Sessions::default());
+
+    let navigation_cache_client = NavigationCacheClientConfig::from_env()
+        .await?
+        .map(NavigationCacheClient::new);
+    // the units samples were read in, for publishing them to mqtt in the units home assistant accepts
+    let sample_units = SampleUnits::default();
+    let measurement_client = measurement_client(
+        &cli,
+        navigation_cache_client,
+        sessions.clone(),
+        Some(sample_units.clone()),
+    )?;
+
+    // the metrics endpoint serves the samples of the last scrape, mostly useful in daemon mode
+    let measurement_client: Box<dyn MeasurementClient<Config>> =
+        match MetricsServerConfig::from_env()? {
+            Some(metrics_server_config) => {
+                let latest_measurements = LatestMeasurements::default();
+                MetricsServer::new(metrics_server_config).spawn(latest_measurements.clone());
+                Box::new(MetricsMeasurementClient::new(
+                    measurement_client,
+                    latest_measurements,
+                ))
+            }
+            None => measurement_client,
+        };
+
+    let mqtt_client = MqttClientConfig::from_env()?
+        .map(|config| Arc::new(MqttClient::new(config, sample_units.clone())));
+    let measurement_client: Box<dyn MeasurementClient<Config>> = match &mqtt_client {
+        Some(mqtt_client) => Box::new(MqttMeasurementClient::new(
+            measurement_client,
+            mqtt_client.clone(),
+        )),
+        None => measurement_client,
+    };
+
+    let measurement_client: Box<dyn MeasurementClient<Config>> =
+        match InfluxdbClientConfig::from_env()? {
+            Some(influxdb_client_config) => Box::new(InfluxdbMeasurementClient::new(
+                measurement_client,
+                InfluxdbClient::new(influxdb_client_config)?,
+            )),
+            None => measurement_client,
+        };
 
     let state_client_config = StateClientConfig::from_env().await?;
     let state_client = StateClient::new(state_client_config);
@@ -23,18 +130,236 @@ pub async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let nats_client_config = NatsClientConfig::from_env().await?;
     let nats_client = NatsClient::new(nats_client_config);
 
-    let config_client_config = ConfigClientConfig::from_env()?;
-    let config_client = ConfigClient::new(config_client_config);
+    let config_client = config_client(&cli)?;
+    // an unsupported config fails at startup rather than on every scrape
+    let config: Config = config_client.read_config_from_file()?;
+    config.check_transport(cli.transport)?;
 
-    let exporter_service_config = ExporterServiceConfig::new(
-        config_client,
-        nats_client,
-        state_client,
-        Box::new(websocket_client),
-    )?;
+    let exporter_service_config =
+        ExporterServiceConfig::new(config_client, nats_client, state_client, measurement_client)?;
     let mut exporter_service = ExporterService::new(exporter_service_config);
 
-    exporter_service.run().await?;
+    let scrape_interval = match scrape_interval {
+        Some(scrape_interval) => scrape_interval,
+        None => {
+            let result = exporter_service.run().await;
+            if let Some(mqtt_client) = &mqtt_client {
+                mqtt_client.disconnect().await;
+            }
+
+            return result;
+        }
+    };
+
+    // the daemon checks its config once when it starts, so ambiguous or misspelled items are logged when the config
+    // is loaded rather than on every scrape
+    if cli.transport == Transport::Websocket {
+        match validations(&cli).await {
+            Ok(validations) => {
+                for validation in validations.iter().filter(|v| !v.passed()) {
+                    warn!("Config check: {}", validation);
+                }
+            }
+            Err(e) => warn!("Checking config against the controller failed: {}", e),
+        }
+    }
+
+    info!("Scraping every {:?} until terminated...", scrape_interval);
+
+    let mut interval = time::interval(scrape_interval);
+    // a scrape that takes longer than the interval delays the next one rather than causing a burst
+    interval.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+    let mut sigterm = signal(SignalKind::terminate())?;
+    let mut sigint = signal(SignalKind::interrupt())?;
+
+    loop {
+        // a running scrape finishes before the signals are checked again, so it's never cut off halfway
+        tokio::select! {
+            _ = interval.tick() => {
+                if let Err(e) = exporter_service.run().await {
+                    error!("Scrape failed, logging in again on the next one: {}", e);
+                }
+            }
+            _ = sigterm.recv() => {
+                info!("Received SIGTERM, shutting down...");
+                break;
+            }
+            _ = sigint.recv() => {
+                info!("Received SIGINT, shutting down...");
+                break;
+            }
+        }
+    }
+
+    if let Some(sessions) = sessions {
+        sessions.close_all().await;
+    }
+    if let Some(mqtt_client) = &mqtt_client {
+        mqtt_client.disconnect().await;
+    }
+
+    Ok(())
+}
+
+// the configured transport's client, with the navigation cache, sessions and sample units only applying to the
+// websocket
+fn measurement_client(
+    cli: &Cli,
+    navigation_cache_client: Option<NavigationCacheClient>,
+    sessions: Option<Sessions>,
+    sample_units: Option<SampleUnits>,
+) -> Result<Box<dyn MeasurementClient<Config>>, Box<dyn std::error::Error>> {
+    Ok(match cli.transport {
+        Transport::Websocket => Box::new(WebsocketClient::new(
+            cli.websocket.config()?,
+            navigation_cache_client,
+            sessions,
+            sample_units,
+        )),
+        Transport::Tcp => Box::new(TcpClient::new(cli.tcp.config()?)),
+    })
+}
+
+fn config_client(cli: &Cli) -> Result<ConfigClient, Box<dyn std::error::Error>> {
+    let config_client_config = match &cli.config {
+        Some(config_path) => ConfigClientConfig::new(config_path.to_string())?,
+        None => ConfigClientConfig::from_env()?,
+    };
+
+    Ok(ConfigClient::new(config_client_config))
+}
+
+// writes a catalog of all items on the heatpump as yaml, to the given file or stdout
+async fn discover(
+    cli: &Cli,
+    output_path: Option<&String>,
+    entity_name: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let websocket_client = WebsocketClient::new(cli.websocket_config()?, None, None, None);
+
+    let catalog = Catalog {
+        sample_configs: websocket_client.discover(entity_name).await?,
+    };
+    let yaml = serde_yaml::to_string(&catalog)?;
+
+    match output_path {
+        Some(output_path) => fs::write(output_path, yaml)?,
+        None => print!("{}", yaml),
+    }
+
+    Ok(())
+}
+
+// scrapes once with the config file and prints the measurements, without touching nats or kubernetes state
+async fn dry_run(cli: &Cli, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let config: Config = config_client(cli)?.read_config_from_file()?;
+
+    let measurements = measurement_client(cli, None, None, None)?.get_measurements(config, None)?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&measurements)?);
+    } else {
+        print!("{}", dry_run::render_table(&measurements));
+    }
+
+    Ok(())
+}
+
+// checks every configured sample against the live controller
+async fn validations(cli: &Cli) -> Result<Vec<SampleValidation>, Box<dyn std::error::Error>> {
+    let config: Config = config_client(cli)?.read_config_from_file()?;
+
+    let websocket_client = WebsocketClient::new(cli.websocket_config()?, None, None, None);
+
+    let mut validations = websocket_client.validate(&config).await;
+    for controller in config.controllers.iter() {
+        validations.extend(
+            websocket_client
+                .for_controller(controller)
+                .validate(&config.for_sample_configs(controller.sample_configs.clone()))
+                .await,
+        );
+    }
+
+    Ok(validations)
+}
+
+// checks every configured sample against the live controller and prints a report
+async fn validate(cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
+    let validations = validations(cli).await?;
+    for validation in validations.iter() {
+        println!("{}", validation);
+    }
+
+    let failed = validations.iter().filter(|v| v.failed()).count();
+    if failed > 0 {
+        return Err(Box::<dyn std::error::Error>::from(format!(
+            "{} of {} samples failed validation",
+            failed,
+            validations.len()
+        )));
+    }
+
+    Ok(())
+}
+
+// prints a single item with its raw and parsed value, for checking one value without editing the config
+async fn get(
+    cli: &Cli,
+    navigation: &str,
+    item: &str,
+    json: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let websocket_client = WebsocketClient::new(cli.websocket_config()?, None, None, None);
+
+    let (page, language) = websocket_client.read_page(navigation).await?;
+    let content_item = match page.get_item(item, language) {
+        Ok(content_item) => content_item,
+        Err(e) => {
+            let names: Vec<&str> = page.items.iter().map(|i| i.name.as_str()).collect();
+            let suggestions = suggest(item, &names);
+            if suggestions.is_empty() {
+                return Err(e);
+            }
+            return Err(Box::<dyn std::error::Error>::from(format!(
+                "{}, did you mean {}?",
+                e,
+                suggestions.join(" or ")
+            )));
+        }
+    };
+    let item_value = ItemValue::new(navigation, content_item, language);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&item_value)?);
+    } else {
+        print!("{}", item_value);
+    }
+
+    Ok(())
+}
+
+// prints every item on a page with its id, value and unit
+async fn dump_page(cli: &Cli, navigation: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let websocket_client = WebsocketClient::new(cli.websocket_config()?, None, None, None);
+
+    let (page, _) = websocket_client.read_page(navigation).await?;
+
+    let mut rows = vec![vec![
+        "ID".to_string(),
+        "NAME".to_string(),
+        "VALUE".to_string(),
+        "UNIT".to_string(),
+    ]];
+    for item in page.items.iter() {
+        rows.push(vec![
+            item.id.clone(),
+            item.name.clone(),
+            item.raw_value.clone(),
+            item.unit.to_string(),
+        ]);
+    }
+    print!("{}", table::render(&rows));
 
     Ok(())
 }
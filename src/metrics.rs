@@ -0,0 +1,359 @@
+use crate::model::Config;
+use chrono::{DateTime, Utc};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{header, Body, Method, Request, Response, Server, StatusCode};
+use jarvis_lib::measurement_client::MeasurementClient;
+use jarvis_lib::model::{Measurement, MetricType, Sample};
+use std::collections::HashSet;
+use std::convert::Infallible;
+use std::env;
+use std::error::Error;
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tracing::{debug, error, info, warn};
+
+const GAUGE_NAME: &str = "jarvis_sample_value";
+const COUNTER_NAME: &str = "jarvis_sample_total";
+const UP_NAME: &str = "jarvis_up";
+const LAST_SCRAPE_NAME: &str = "jarvis_last_scrape_timestamp_seconds";
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+// the measurements of the last successful scrape and whether the latest one succeeded, so stale values can be told
+// apart from current ones
+#[derive(Debug, Default)]
+struct LatestScrape {
+    measurements: Vec<Measurement>,
+    succeeded_at: Option<DateTime<Utc>>,
+    up: bool,
+}
+
+#[derive(Clone, Default)]
+pub struct LatestMeasurements {
+    scrape: Arc<Mutex<LatestScrape>>,
+}
+
+impl LatestMeasurements {
+    pub fn set(&self, measurements: Vec<Measurement>) {
+        for labels in duplicate_labels(&measurements) {
+            warn!(
+                "Sample with labels {} occurs more than once, only the first is served on /metrics",
+                labels
+            );
+        }
+
+        *self.scrape.lock().unwrap() = LatestScrape {
+            measurements,
+            succeeded_at: Some(Utc::now()),
+            up: true,
+        };
+    }
+
+    // keeps serving the samples of the last successful scrape, marked as down
+    pub fn set_failed(&self) {
+        self.scrape.lock().unwrap().up = false;
+    }
+
+    pub fn render(&self) -> String {
+        let scrape = self.scrape.lock().unwrap();
+        render_scrape(&scrape) + &render(&scrape.measurements)
+    }
+}
+
+pub struct MetricsMeasurementClient {
+    measurement_client: Box<dyn MeasurementClient<Config>>,
+    latest_measurements: LatestMeasurements,
+}
+
+impl MetricsMeasurementClient {
+    pub fn new(
+        measurement_client: Box<dyn MeasurementClient<Config>>,
+        latest_measurements: LatestMeasurements,
+    ) -> Self {
+        Self {
+            measurement_client,
+            latest_measurements,
+        }
+    }
+}
+
+impl MeasurementClient<Config> for MetricsMeasurementClient {
+    fn get_measurements(
+        &self,
+        config: Config,
+        last_measurement: Option<Vec<Measurement>>,
+    ) -> Result<Vec<Measurement>, Box<dyn Error>> {
+        let measurements = match self
+            .measurement_client
+            .get_measurements(config, last_measurement)
+        {
+            Ok(measurements) => measurements,
+            Err(e) => {
+                self.latest_measurements.set_failed();
+                return Err(e);
+            }
+        };
+
+        self.latest_measurements.set(measurements.clone());
+
+        Ok(measurements)
+    }
+}
+
+pub struct MetricsServerConfig {
+    address: SocketAddr,
+}
+
+impl MetricsServerConfig {
+    pub fn new(address: SocketAddr) -> Result<Self, Box<dyn Error>> {
+        debug!("MetricsServerConfig::new(address: {})", address);
+
+        Ok(Self { address })
+    }
+
+    pub fn from_env() -> Result<Option<Self>, Box<dyn Error>> {
+        match env::var("METRICS_PORT") {
+            Ok(port) if !port.is_empty() => {
+                let port: u16 = port.parse()?;
+                Ok(Some(Self::new(SocketAddr::from(([0, 0, 0, 0], port)))?))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+pub struct MetricsServer {
+    config: MetricsServerConfig,
+}
+
+impl MetricsServer {
+    pub fn new(config: MetricsServerConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn spawn(self, latest_measurements: LatestMeasurements) {
+        let make_service = make_service_fn(move |_| {
+            let latest_measurements = latest_measurements.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    let latest_measurements = latest_measurements.clone();
+                    async move { Ok::<_, Infallible>(handle(request, &latest_measurements)) }
+                }))
+            }
+        });
+
+        let address = self.config.address;
+        info!("Serving metrics on http://{}/metrics", address);
+        tokio::spawn(async move {
+            if let Err(e) = Server::bind(&address).serve(make_service).await {
+                error!("Metrics server failed: {}", e);
+            }
+        });
+    }
+}
+
+fn handle(request: Request<Body>, latest_measurements: &LatestMeasurements) -> Response<Body> {
+    match (request.method(), request.uri().path()) {
+        (&Method::GET, "/metrics") => Response::builder()
+            .header(header::CONTENT_TYPE, CONTENT_TYPE)
+            .body(Body::from(latest_measurements.render()))
+            .unwrap(),
+        _ => Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty())
+            .unwrap(),
+    }
+}
+
+fn render_scrape(scrape: &LatestScrape) -> String {
+    let mut output = String::new();
+
+    let _ = writeln!(
+        output,
+        "# HELP {} Whether the last scrape succeeded.",
+        UP_NAME
+    );
+    let _ = writeln!(output, "# TYPE {} gauge", UP_NAME);
+    let _ = writeln!(output, "{} {}", UP_NAME, if scrape.up { 1 } else { 0 });
+
+    if let Some(succeeded_at) = scrape.succeeded_at {
+        let _ = writeln!(
+            output,
+            "# HELP {} Time of the last successful scrape.",
+            LAST_SCRAPE_NAME
+        );
+        let _ = writeln!(output, "# TYPE {} gauge", LAST_SCRAPE_NAME);
+        let _ = writeln!(
+            output,
+            "{} {}",
+            LAST_SCRAPE_NAME,
+            succeeded_at.timestamp_millis() as f64 / 1000.0
+        );
+    }
+
+    output
+}
+
+// prometheus text format with a gauge and a counter family; the sample type is a label since every sample type
+// comes with its own unit. A label set can only occur once per family, so of duplicates only the first is served
+pub fn render(measurements: &[Measurement]) -> String {
+    let mut output = String::new();
+
+    for (name, metric_type, help) in [
+        (GAUGE_NAME, MetricType::Gauge, "gauge"),
+        (COUNTER_NAME, MetricType::Counter, "counter"),
+    ] {
+        let _ = writeln!(
+            output,
+            "# HELP {} Latest value of each {} sample.",
+            name, help
+        );
+        let _ = writeln!(output, "# TYPE {} {}", name, help);
+
+        let mut seen = HashSet::new();
+        for measurement in measurements.iter() {
+            for sample in measurement
+                .samples
+                .iter()
+                .filter(|s| s.metric_type == metric_type)
+            {
+                let labels = labels(&measurement.location, sample);
+                if seen.insert(labels.clone()) {
+                    let _ = writeln!(output, "{}{} {}", name, labels, sample.value);
+                }
+            }
+        }
+    }
+
+    output
+}
+
+fn duplicate_labels(measurements: &[Measurement]) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut duplicates = Vec::new();
+    for measurement in measurements.iter() {
+        for sample in measurement.samples.iter() {
+            let labels = labels(&measurement.location, sample);
+            if !seen.insert((sample.metric_type == MetricType::Counter, labels.clone())) {
+                duplicates.push(labels);
+            }
+        }
+    }
+
+    duplicates
+}
+
+fn labels(location: &str, sample: &Sample) -> String {
+    format!(
+        "{{entity_name=\"{}\",sample_name=\"{}\",sample_type=\"{}\",location=\"{}\"}}",
+        escape(&sample.entity_name),
+        escape(&sample.sample_name),
+        snake_case(&format!("{:?}", sample.sample_type)),
+        escape(location)
+    )
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+// type names as labels, for example electricity_consumption for SampleType::ElectricityConsumption
+pub fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            snake.push('_');
+        }
+        snake.extend(c.to_lowercase());
+    }
+    snake
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use jarvis_lib::model::{EntityType, SampleType};
+
+    fn sample(sample_name: &str, sample_type: SampleType, metric_type: MetricType) -> Sample {
+        Sample {
+            entity_type: EntityType::Device,
+            entity_name: "Alpha Innotec SWCV 92K3".to_string(),
+            sample_type,
+            sample_name: sample_name.to_string(),
+            metric_type,
+            value: 12.5,
+        }
+    }
+
+    #[test]
+    fn render_maps_metric_types_to_prometheus_types() {
+        let measurement = Measurement {
+            id: "cc6e17bb-fd60-4dde-acc3-0cda7d752ffe".to_string(),
+            source: "jarvis-alpha-innotec-exporter".to_string(),
+            location: "My Home".to_string(),
+            samples: vec![
+                sample("Aanvoer", SampleType::Temperature, MetricType::Gauge),
+                sample(
+                    "Warmtepomp",
+                    SampleType::ElectricityConsumption,
+                    MetricType::Counter,
+                ),
+            ],
+            measured_at_time: Utc::now(),
+        };
+
+        // act
+        let output = render(&[measurement]);
+
+        assert!(output.contains("# TYPE jarvis_sample_value gauge\n"));
+        assert!(output.contains("# TYPE jarvis_sample_total counter\n"));
+        assert!(output.contains("jarvis_sample_value{entity_name=\"Alpha Innotec SWCV 92K3\",sample_name=\"Aanvoer\",sample_type=\"temperature\",location=\"My Home\"} 12.5\n"));
+        assert!(output.contains("jarvis_sample_total{entity_name=\"Alpha Innotec SWCV 92K3\",sample_name=\"Warmtepomp\",sample_type=\"electricity_consumption\",location=\"My Home\"} 12.5\n"));
+    }
+
+    #[test]
+    fn render_serves_duplicate_label_sets_once() {
+        let measurement = Measurement {
+            id: "cc6e17bb-fd60-4dde-acc3-0cda7d752ffe".to_string(),
+            source: "jarvis-alpha-innotec-exporter".to_string(),
+            location: "My Home".to_string(),
+            samples: vec![
+                sample("Aanvoer", SampleType::Temperature, MetricType::Gauge),
+                sample("Aanvoer", SampleType::Temperature, MetricType::Gauge),
+            ],
+            measured_at_time: Utc::now(),
+        };
+
+        // act
+        let output = render(std::slice::from_ref(&measurement));
+
+        assert_eq!(output.matches("sample_name=\"Aanvoer\"").count(), 1);
+        assert_eq!(duplicate_labels(&[measurement]).len(), 1);
+    }
+
+    #[test]
+    fn latest_measurements_marks_failed_scrape_as_down() {
+        let latest_measurements = LatestMeasurements::default();
+        assert!(latest_measurements.render().contains("jarvis_up 0\n"));
+
+        // act
+        latest_measurements.set(vec![]);
+        let up = latest_measurements.render();
+        latest_measurements.set_failed();
+        let down = latest_measurements.render();
+
+        assert!(up.contains("jarvis_up 1\n"));
+        assert!(up.contains("# TYPE jarvis_last_scrape_timestamp_seconds gauge\n"));
+        assert!(down.contains("jarvis_up 0\n"));
+        assert!(down.contains("jarvis_last_scrape_timestamp_seconds "));
+    }
+
+    #[test]
+    fn escape_escapes_quotes_backslashes_and_newlines() {
+        assert_eq!(escape("Dagen \"Ma\\Di\"\n"), "Dagen \\\"Ma\\\\Di\\\"\\n");
+    }
+}
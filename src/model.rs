@@ -1,13 +1,62 @@
+use crate::language::Language;
+use crate::unit::Unit;
+use crate::value::ValueMapping;
 use jarvis_lib::config_client::SetDefaults;
 use jarvis_lib::model::{EntityType, MetricType, SampleType};
 use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::str::FromStr;
 
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct Config {
     pub location: String,
     pub sanitize_samples: bool,
+    // language of the controller display, detected from the navigation when not set
+    pub language: Option<Language>,
+    // samples that may fail before the whole measurement fails; without it only a run where all samples fail fails
+    pub max_failed_samples: Option<usize>,
+    // adds a self-monitoring sample with the number of failed samples to every measurement
+    #[serde(default)]
+    pub publish_failed_samples: bool,
+    // samples of the controller set by the WEBSOCKET_HOST_IP, WEBSOCKET_HOST_PORT and WEBSOCKET_LOGIN_CODE envvars
+    #[serde(default)]
     pub sample_configs: Vec<ConfigSample>,
+    // further heatpumps, for example in a cascade, scraped concurrently with their own samples
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub controllers: Vec<ConfigController>,
+    // publish the samples of all controllers as one measurement instead of a measurement per controller
+    #[serde(default)]
+    pub combine_measurements: bool,
+}
+
+impl Config {
+    // the config for scraping a single controller, sharing the settings that aren't controller specific
+    pub fn for_sample_configs(&self, sample_configs: Vec<ConfigSample>) -> Config {
+        Config {
+            location: self.location.clone(),
+            sanitize_samples: self.sanitize_samples,
+            language: self.language,
+            max_failed_samples: self.max_failed_samples,
+            publish_failed_samples: self.publish_failed_samples,
+            sample_configs,
+            controllers: vec![],
+            combine_measurements: false,
+        }
+    }
+}
+
+impl Config {
+    // further controllers are only scraped over the websocket, the tcp client would silently leave them out
+    pub fn check_transport(&self, transport: Transport) -> Result<(), Box<dyn Error>> {
+        if transport == Transport::Tcp && !self.controllers.is_empty() {
+            return Err(Box::<dyn Error>::from(
+                "Controllers are only supported by the websocket transport".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
 }
 
 impl SetDefaults for Config {
@@ -15,14 +64,43 @@ impl SetDefaults for Config {
         for sample_config in self.sample_configs.iter_mut() {
             sample_config.set_defaults()
         }
+        for controller in self.controllers.iter_mut() {
+            controller.set_defaults()
+        }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigController {
+    pub host: String,
+    // port and login code default to the ones of the controller set by envvars
+    pub port: Option<u32>,
+    pub login_code: Option<String>,
+    // entity name for the samples of this controller that don't set one
+    pub entity_name: Option<String>,
+    pub sample_configs: Vec<ConfigSample>,
+}
+
+impl ConfigController {
+    pub fn set_defaults(&mut self) {
+        for sample_config in self.sample_configs.iter_mut() {
+            if sample_config.entity_name.is_empty() {
+                if let Some(entity_name) = &self.entity_name {
+                    sample_config.entity_name = entity_name.clone();
+                }
+            }
+            sample_config.set_defaults()
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ConfigSample {
     // default jarvis config for sample
     pub entity_type: EntityType,
+    #[serde(default)]
     pub entity_name: String,
     pub sample_type: SampleType,
     pub sample_name: String,
@@ -30,8 +108,34 @@ pub struct ConfigSample {
 
     // alpha innotec specific config for sample
     pub value_multiplier: f64,
+    #[serde(default)]
     pub navigation: String,
+    #[serde(default)]
     pub item: String,
+    // disambiguate items sharing a name on one page, by element id, zero-based occurrence or unit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub item_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub item_occurrence: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub item_unit: Option<Unit>,
+    // maps text values such as Aan/Uit to numbers, either onOff or a custom text to number map
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value_mapping: Option<ValueMapping>,
+    // what to do when the controller shows --- instead of a value
+    #[serde(default, skip_serializing_if = "MissingValuePolicy::is_default")]
+    pub missing_value: MissingValuePolicy,
+    // essential sample, any error reading it fails the whole run instead of only this sample
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub required: bool,
+
+    // luxtronik binary protocol indices, only used by the tcp transport
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub calculation: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parameter: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub visibility: Option<usize>,
 }
 
 impl ConfigSample {
@@ -42,6 +146,80 @@ impl ConfigSample {
     }
 }
 
+#[cfg(test)]
+impl ConfigSample {
+    // a gauge without any of the optional settings; tests override the fields they're about with struct update syntax
+    pub fn for_test(
+        sample_type: SampleType,
+        sample_name: &str,
+        navigation: &str,
+        item: &str,
+    ) -> Self {
+        Self {
+            entity_type: EntityType::Device,
+            entity_name: "Alpha Innotec SWCV 92K3".to_string(),
+            sample_type,
+            sample_name: sample_name.to_string(),
+            metric_type: MetricType::Gauge,
+            value_multiplier: 1.0,
+            navigation: navigation.to_string(),
+            item: item.to_string(),
+            item_id: None,
+            item_occurrence: None,
+            item_unit: None,
+            value_mapping: None,
+            missing_value: MissingValuePolicy::Zero,
+            required: false,
+            calculation: None,
+            parameter: None,
+            visibility: None,
+        }
+    }
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum MissingValuePolicy {
+    #[default]
+    Zero,
+    Skip,
+    LastKnown,
+    Fail,
+}
+
+impl MissingValuePolicy {
+    fn is_default(&self) -> bool {
+        *self == MissingValuePolicy::default()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Transport {
+    // Lux_WS websocket on port 8214, addressing items by localized page and item name
+    Websocket,
+    // Luxtronik 2 binary protocol on port 8889, addressing values by numeric index
+    Tcp,
+}
+
+impl FromStr for Transport {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "websocket" | "ws" => Ok(Transport::Websocket),
+            "tcp" => Ok(Transport::Tcp),
+            _ => Err(Box::<dyn Error>::from(format!(
+                "Transport {} is not supported, use websocket or tcp",
+                s
+            ))),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -76,4 +254,83 @@ mod tests {
         );
         assert_eq!(config.sample_configs[0].item, "Aanvoer".to_string());
     }
+
+    #[test]
+    fn read_config_from_file_defaults_optional_sample_fields() {
+        let config_client =
+            ConfigClient::new(ConfigClientConfig::new("test-config.yaml".to_string()).unwrap());
+
+        let config: Config = config_client.read_config_from_file().unwrap();
+
+        assert_eq!(config.sample_configs[0].calculation, None);
+        assert_eq!(
+            config.sample_configs[0].missing_value,
+            MissingValuePolicy::Zero
+        );
+        assert!(!config.sample_configs[0].required);
+    }
+
+    #[test]
+    fn set_defaults_applies_controller_entity_name_to_its_samples() {
+        let mut config: Config = serde_yaml::from_str(
+            "location: My Home
+sanitizeSamples: false
+controllers:
+- host: 192.168.195.5
+  entityName: Alpha Innotec LWD 70A
+  sampleConfigs:
+  - entityType: ENTITY_TYPE_DEVICE
+    sampleType: SAMPLE_TYPE_TEMPERATURE
+    sampleName: Aanvoer
+    metricType: METRIC_TYPE_GAUGE
+    valueMultiplier: 0
+    navigation: Informatie > Temperaturen
+    item: Aanvoer
+",
+        )
+        .unwrap();
+
+        // act
+        config.set_defaults();
+
+        assert!(config.sample_configs.is_empty());
+        assert!(!config.combine_measurements);
+        assert_eq!(config.controllers[0].port, None);
+        let sample_config = &config.controllers[0].sample_configs[0];
+        assert_eq!(
+            sample_config.entity_name,
+            "Alpha Innotec LWD 70A".to_string()
+        );
+        assert_eq!(sample_config.value_multiplier, 1.0);
+    }
+
+    #[test]
+    fn check_transport_rejects_controllers_over_tcp() {
+        let config: Config = serde_yaml::from_str(
+            "location: My Home
+sanitizeSamples: false
+controllers:
+- host: 192.168.195.5
+  sampleConfigs: []
+",
+        )
+        .unwrap();
+
+        // act
+        let tcp = config.check_transport(Transport::Tcp);
+        let websocket = config.check_transport(Transport::Websocket);
+
+        assert!(tcp.is_err());
+        assert!(websocket.is_ok());
+    }
+
+    #[test]
+    fn transport_from_str_returns_transport_case_insensitive() {
+        assert_eq!(
+            "websocket".parse::<Transport>().unwrap(),
+            Transport::Websocket
+        );
+        assert_eq!("TCP".parse::<Transport>().unwrap(), Transport::Tcp);
+        assert!("serial".parse::<Transport>().is_err());
+    }
 }
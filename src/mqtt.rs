@@ -0,0 +1,537 @@
+use crate::model::Config;
+use crate::unit::{SampleUnits, Unit};
+use jarvis_lib::measurement_client::MeasurementClient;
+use jarvis_lib::model::{Measurement, MetricType, Sample, SampleType};
+use rumqttc::{AsyncClient, Event, MqttOptions, Outgoing, QoS};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+const MANUFACTURER: &str = "Alpha Innotec";
+
+#[derive(Debug)]
+pub struct MqttClientConfig {
+    host: String,
+    port: u16,
+    client_id: String,
+    credentials: Option<(String, String)>,
+    topic_prefix: String,
+    discovery_prefix: String,
+}
+
+impl MqttClientConfig {
+    pub fn new(
+        host: String,
+        port: u16,
+        client_id: String,
+        credentials: Option<(String, String)>,
+        topic_prefix: String,
+        discovery_prefix: String,
+    ) -> Result<Self, Box<dyn Error>> {
+        let config = Self {
+            host,
+            port,
+            client_id,
+            credentials,
+            topic_prefix,
+            discovery_prefix,
+        };
+
+        debug!(
+            "MqttClientConfig::new(host: {}, port: {}, client_id: {}, topic_prefix: {}, discovery_prefix: {})",
+            config.host, config.port, config.client_id, config.topic_prefix, config.discovery_prefix
+        );
+
+        Ok(config)
+    }
+
+    pub fn from_env() -> Result<Option<Self>, Box<dyn Error>> {
+        let host = match env::var("MQTT_HOST") {
+            Ok(host) if !host.is_empty() => host,
+            _ => return Ok(None),
+        };
+        let port: u16 = env::var("MQTT_PORT")
+            .unwrap_or_else(|_| "1883".to_string())
+            .parse()?;
+        let client_id = env::var("MQTT_CLIENT_ID")
+            .unwrap_or_else(|_| "jarvis-alpha-innotec-exporter".to_string());
+        let credentials = match (env::var("MQTT_USERNAME"), env::var("MQTT_PASSWORD")) {
+            (Ok(username), Ok(password)) if !username.is_empty() => Some((username, password)),
+            _ => None,
+        };
+        let topic_prefix = env::var("MQTT_TOPIC_PREFIX").unwrap_or_else(|_| "jarvis".to_string());
+        let discovery_prefix =
+            env::var("MQTT_DISCOVERY_PREFIX").unwrap_or_else(|_| "homeassistant".to_string());
+
+        Ok(Some(Self::new(
+            host,
+            port,
+            client_id,
+            credentials,
+            topic_prefix,
+            discovery_prefix,
+        )?))
+    }
+}
+
+pub struct MqttClient {
+    config: MqttClientConfig,
+    client: AsyncClient,
+    event_loop: Mutex<Option<JoinHandle<()>>>,
+    // discovery config is retained by the broker, so it's only published again when it changes, like its unit
+    discovered: Mutex<HashMap<String, Vec<u8>>>,
+    sample_units: SampleUnits,
+}
+
+impl MqttClient {
+    pub fn new(config: MqttClientConfig, sample_units: SampleUnits) -> Self {
+        let mut options = MqttOptions::new(&config.client_id, &config.host, config.port);
+        options.set_keep_alive(Duration::from_secs(30));
+        if let Some((username, password)) = &config.credentials {
+            options.set_credentials(username, password);
+        }
+
+        let (client, mut event_loop) = AsyncClient::new(options, 100);
+
+        // the event loop does the actual network io and reconnects after errors, until disconnected
+        let event_loop = tokio::spawn(async move {
+            loop {
+                match event_loop.poll().await {
+                    Ok(Event::Outgoing(Outgoing::Disconnect)) => break,
+                    Ok(_) => {}
+                    Err(e) => {
+                        warn!("Mqtt connection failed, reconnecting: {}", e);
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                    }
+                }
+            }
+        });
+
+        Self {
+            config,
+            client,
+            event_loop: Mutex::new(Some(event_loop)),
+            discovered: Mutex::new(HashMap::new()),
+            sample_units,
+        }
+    }
+
+    pub fn publish(&self, measurements: &[Measurement]) -> Result<(), Box<dyn Error>> {
+        let mut states: BTreeMap<String, BTreeMap<String, f64>> = BTreeMap::new();
+        let mut entity_ids = HashMap::new();
+        let mut sample_ids: HashMap<String, HashMap<String, String>> = HashMap::new();
+
+        // invalid samples such as the failed samples count aren't heatpump readings
+        for sample in measurements
+            .iter()
+            .flat_map(|m| m.samples.iter())
+            .filter(|s| s.sample_type != SampleType::Invalid)
+        {
+            let unit = self
+                .sample_units
+                .get(&sample.entity_name, &sample.sample_name);
+            let entity_id = unique_slug(&sample.entity_name, &mut entity_ids);
+            let sample_id = unique_slug(
+                &sample.sample_name,
+                sample_ids.entry(entity_id.clone()).or_default(),
+            );
+            let state_topic = format!("{}/{}/state", self.config.topic_prefix, entity_id);
+
+            let discovery_topic = format!(
+                "{}/sensor/{}/{}/config",
+                self.config.discovery_prefix, entity_id, sample_id
+            );
+            let discovery_config = serde_json::to_vec(&discovery_config(
+                sample,
+                &entity_id,
+                &sample_id,
+                unit.as_ref(),
+                &state_topic,
+            ))?;
+            let announced =
+                self.discovered.lock().unwrap().get(&discovery_topic) == Some(&discovery_config);
+            if !announced && self.try_publish(&discovery_topic, true, discovery_config.clone()) {
+                self.discovered
+                    .lock()
+                    .unwrap()
+                    .insert(discovery_topic, discovery_config);
+            }
+
+            let factor = home_assistant_unit(sample.sample_type, unit.as_ref())
+                .map(|(_, _, factor)| factor)
+                .unwrap_or(1.0);
+            states
+                .entry(state_topic)
+                .or_default()
+                .insert(sample_id, sample.value * factor);
+        }
+
+        let mut published = 0;
+        for (state_topic, state) in states.iter() {
+            if self.try_publish(state_topic, false, serde_json::to_vec(state)?) {
+                published += 1;
+            }
+        }
+
+        info!(
+            "Published {} of {} entities to mqtt",
+            published,
+            states.len()
+        );
+
+        Ok(())
+    }
+
+    // queues the message without waiting for room in the queue, which doesn't free up while the broker is down
+    fn try_publish(&self, topic: &str, retain: bool, payload: Vec<u8>) -> bool {
+        match self
+            .client
+            .try_publish(topic, QoS::AtLeastOnce, retain, payload)
+        {
+            Ok(()) => true,
+            Err(e) => {
+                warn!("Queueing mqtt message for {} failed: {}", topic, e);
+                false
+            }
+        }
+    }
+
+    // sends the queued messages and disconnects, so nothing is lost when the exporter exits
+    pub async fn disconnect(&self) {
+        if let Err(e) = self.client.try_disconnect() {
+            warn!("Disconnecting from mqtt failed: {}", e);
+        }
+
+        let event_loop = self.event_loop.lock().unwrap().take();
+        if let Some(event_loop) = event_loop {
+            if tokio::time::timeout(Duration::from_secs(10), event_loop)
+                .await
+                .is_err()
+            {
+                warn!("Mqtt didn't disconnect within 10 seconds");
+            }
+        }
+    }
+}
+
+pub struct MqttMeasurementClient {
+    measurement_client: Box<dyn MeasurementClient<Config>>,
+    mqtt_client: Arc<MqttClient>,
+}
+
+impl MqttMeasurementClient {
+    pub fn new(
+        measurement_client: Box<dyn MeasurementClient<Config>>,
+        mqtt_client: Arc<MqttClient>,
+    ) -> Self {
+        Self {
+            measurement_client,
+            mqtt_client,
+        }
+    }
+}
+
+impl MeasurementClient<Config> for MqttMeasurementClient {
+    fn get_measurements(
+        &self,
+        config: Config,
+        last_measurement: Option<Vec<Measurement>>,
+    ) -> Result<Vec<Measurement>, Box<dyn Error>> {
+        let measurements = self
+            .measurement_client
+            .get_measurements(config, last_measurement)?;
+
+        // nats stays the primary output, a broker that's down doesn't fail the run
+        if let Err(e) = self.mqtt_client.publish(&measurements) {
+            warn!("Publishing to mqtt failed: {}", e);
+        }
+
+        Ok(measurements)
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct DiscoveryConfig {
+    name: String,
+    unique_id: String,
+    state_topic: String,
+    value_template: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    device_class: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    state_class: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    unit_of_measurement: Option<&'static str>,
+    device: DiscoveryDevice,
+}
+
+#[derive(Serialize, Debug)]
+struct DiscoveryDevice {
+    identifiers: Vec<String>,
+    name: String,
+    manufacturer: &'static str,
+}
+
+fn discovery_config(
+    sample: &Sample,
+    entity_id: &str,
+    sample_id: &str,
+    unit: Option<&Unit>,
+    state_topic: &str,
+) -> DiscoveryConfig {
+    let unit = home_assistant_unit(sample.sample_type, unit);
+
+    DiscoveryConfig {
+        name: sample.sample_name.clone(),
+        unique_id: format!("{}_{}", entity_id, sample_id),
+        state_topic: state_topic.to_string(),
+        value_template: format!("{{{{ value_json['{}'] }}}}", sample_id),
+        device_class: unit.map(|(device_class, _, _)| device_class),
+        state_class: match sample.metric_type {
+            MetricType::Gauge => Some("measurement"),
+            MetricType::Counter => Some("total_increasing"),
+            _ => None,
+        },
+        unit_of_measurement: unit.map(|(_, unit, _)| unit),
+        device: DiscoveryDevice {
+            identifiers: vec![entity_id.to_string()],
+            name: sample.entity_name.clone(),
+            manufacturer: MANUFACTURER,
+        },
+    }
+}
+
+// home assistant device class and unit per sample type, with the factor from the exported si value; the units are
+// the ones the controller displays, since home assistant doesn't accept all si units. Samples that weren't read in a
+// known unit aren't in si, so they're published as is without a unit
+fn home_assistant_unit(
+    sample_type: SampleType,
+    unit: Option<&Unit>,
+) -> Option<(&'static str, &'static str, f64)> {
+    unit?;
+
+    match sample_type {
+        SampleType::Temperature => Some(("temperature", "°C", 1.0)),
+        SampleType::Pressure => Some(("pressure", "bar", 1e-5)),
+        SampleType::Flow => Some(("volume_flow_rate", "m³/h", 3600.0)),
+        SampleType::Energy => Some(("energy", "kWh", 1.0 / 3_600_000.0)),
+        SampleType::Time => Some(("duration", "h", 1.0 / 3600.0)),
+        _ => None,
+    }
+}
+
+fn slug(name: &str) -> String {
+    let mut slug = String::new();
+    for c in name.to_lowercase().chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('_') {
+            slug.push('_');
+        }
+    }
+    slug.trim_end_matches('_').to_string()
+}
+
+// slug that isn't taken by another name yet, numbered when different names end up with the same slug
+fn unique_slug(name: &str, taken: &mut HashMap<String, String>) -> String {
+    let slug = slug(name);
+    let mut unique_slug = slug.clone();
+    let mut number = 1;
+    while let Some(taken_by) = taken.get(&unique_slug) {
+        if taken_by == name {
+            return unique_slug;
+        }
+        number += 1;
+        unique_slug = format!("{}_{}", slug, number);
+    }
+
+    if number > 1 {
+        warn!(
+            "Name {} has the same mqtt slug as another name, using {}",
+            name, unique_slug
+        );
+    }
+    taken.insert(unique_slug.clone(), name.to_string());
+    unique_slug
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use jarvis_lib::model::EntityType;
+
+    #[test]
+    fn slug_replaces_other_characters_with_underscores() {
+        assert_eq!(slug("Alpha Innotec SWCV 92K3"), "alpha_innotec_swcv_92k3");
+        assert_eq!(slug("Retour berekend"), "retour_berekend");
+        assert_eq!(slug("Bron-in (°C)"), "bron_in_c");
+    }
+
+    #[test]
+    fn unique_slug_numbers_different_names_with_same_slug() {
+        let mut taken = HashMap::new();
+
+        // act
+        let slugs = vec![
+            unique_slug("Bron-in", &mut taken),
+            unique_slug("Bron in", &mut taken),
+            unique_slug("Bron-in", &mut taken),
+            unique_slug("Bron (in)", &mut taken),
+        ];
+
+        assert_eq!(slugs, vec!["bron_in", "bron_in_2", "bron_in", "bron_in_3"]);
+    }
+
+    #[tokio::test]
+    async fn publish_doesnt_block_without_broker() {
+        let mqtt_client = MqttClient::new(
+            MqttClientConfig::new(
+                "127.0.0.1".to_string(),
+                1,
+                "jarvis-alpha-innotec-exporter".to_string(),
+                None,
+                "jarvis".to_string(),
+                "homeassistant".to_string(),
+            )
+            .unwrap(),
+            SampleUnits::default(),
+        );
+        // more discovery and state messages than fit in the queue
+        let measurement = Measurement {
+            id: "cc6e17bb-fd60-4dd4-9f73-5af1c5d3c2b6".to_string(),
+            source: "jarvis-alpha-innotec-exporter".to_string(),
+            location: "My address".to_string(),
+            samples: (0..150)
+                .map(|i| Sample {
+                    entity_type: EntityType::Device,
+                    entity_name: format!("Alpha Innotec {}", i),
+                    sample_type: SampleType::Temperature,
+                    sample_name: "Aanvoer".to_string(),
+                    metric_type: MetricType::Gauge,
+                    value: 35.4,
+                })
+                .collect(),
+            measured_at_time: Utc::now(),
+        };
+
+        // act
+        let result = mqtt_client.publish(&[measurement]);
+
+        assert!(result.is_ok());
+        // discovery that didn't fit in the queue is published again with the next measurement
+        assert!(mqtt_client.discovered.lock().unwrap().len() < 150);
+    }
+
+    #[tokio::test]
+    async fn publish_announces_discovery_again_when_unit_changes() {
+        let sample_units = SampleUnits::default();
+        let mqtt_client = MqttClient::new(
+            MqttClientConfig::new(
+                "127.0.0.1".to_string(),
+                1,
+                "jarvis-alpha-innotec-exporter".to_string(),
+                None,
+                "jarvis".to_string(),
+                "homeassistant".to_string(),
+            )
+            .unwrap(),
+            sample_units.clone(),
+        );
+        let measurement = Measurement {
+            id: "cc6e17bb-fd60-4dd4-9f73-5af1c5d3c2b6".to_string(),
+            source: "jarvis-alpha-innotec-exporter".to_string(),
+            location: "My address".to_string(),
+            samples: vec![Sample {
+                entity_type: EntityType::Device,
+                entity_name: "Alpha Innotec SWCV 92K3".to_string(),
+                sample_type: SampleType::Pressure,
+                sample_name: "Hogedruk".to_string(),
+                metric_type: MetricType::Gauge,
+                value: 1_850_000.0,
+            }],
+            measured_at_time: Utc::now(),
+        };
+        let discovery_topic = "homeassistant/sensor/alpha_innotec_swcv_92k3/hogedruk/config";
+        mqtt_client
+            .publish(std::slice::from_ref(&measurement))
+            .unwrap();
+        let announced = mqtt_client.discovered.lock().unwrap()[discovery_topic].clone();
+        sample_units.set("Alpha Innotec SWCV 92K3", "Hogedruk", Some(Unit::Bar));
+
+        // act
+        mqtt_client.publish(&[measurement]).unwrap();
+
+        let reannounced = mqtt_client.discovered.lock().unwrap()[discovery_topic].clone();
+        assert!(!String::from_utf8(announced)
+            .unwrap()
+            .contains("\"unit_of_measurement\""));
+        assert!(String::from_utf8(reannounced)
+            .unwrap()
+            .contains("\"unit_of_measurement\":\"bar\""));
+    }
+
+    #[test]
+    fn discovery_config_maps_sample_and_metric_type() {
+        let sample = Sample {
+            entity_type: EntityType::Device,
+            entity_name: "Alpha Innotec SWCV 92K3".to_string(),
+            sample_type: SampleType::Energy,
+            sample_name: "Warmtehoeveelheid verwarmen".to_string(),
+            metric_type: MetricType::Counter,
+            value: 3_600_000.0,
+        };
+
+        // act
+        let config = discovery_config(
+            &sample,
+            "alpha_innotec_swcv_92k3",
+            "warmtehoeveelheid_verwarmen",
+            Some(&Unit::KilowattHour),
+            "jarvis/alpha_innotec_swcv_92k3/state",
+        );
+
+        assert_eq!(
+            config.unique_id,
+            "alpha_innotec_swcv_92k3_warmtehoeveelheid_verwarmen".to_string()
+        );
+        assert_eq!(
+            config.value_template,
+            "{{ value_json['warmtehoeveelheid_verwarmen'] }}".to_string()
+        );
+        assert_eq!(config.device_class, Some("energy"));
+        assert_eq!(config.state_class, Some("total_increasing"));
+        assert_eq!(config.unit_of_measurement, Some("kWh"));
+    }
+
+    #[test]
+    fn discovery_config_leaves_out_unit_of_sample_without_known_unit() {
+        let sample = Sample {
+            entity_type: EntityType::Device,
+            entity_name: "Alpha Innotec SWCV 92K3".to_string(),
+            sample_type: SampleType::Pressure,
+            sample_name: "Hogedruk".to_string(),
+            metric_type: MetricType::Gauge,
+            value: 18.5,
+        };
+
+        // act
+        let config = discovery_config(
+            &sample,
+            "alpha_innotec_swcv_92k3",
+            "hogedruk",
+            None,
+            "jarvis/alpha_innotec_swcv_92k3/state",
+        );
+
+        assert_eq!(config.device_class, None);
+        assert_eq!(config.unit_of_measurement, None);
+        assert_eq!(home_assistant_unit(SampleType::Pressure, None), None);
+        assert!(home_assistant_unit(SampleType::Pressure, Some(&Unit::Bar)).is_some());
+    }
+}
@@ -0,0 +1,219 @@
+use crate::language::Language;
+use chrono::Utc;
+use jarvis_lib::model::{EntityType, Measurement, MetricType, Sample, SampleType};
+use jarvis_lib::state_client::{StateClient, StateClientConfig};
+use std::collections::BTreeMap;
+use std::env;
+use std::error::Error;
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+
+const SOURCE: &str = "navigation-cache";
+
+// page ids resolved from the navigation tree; they're stable for a controller until its firmware changes
+#[derive(Debug, Clone, PartialEq)]
+pub struct NavigationCache {
+    pub controller: String,
+    // id of the navigation root, which moves with every firmware build
+    pub firmware: String,
+    pub language: Option<Language>,
+    pub page_ids: BTreeMap<String, String>,
+}
+
+impl NavigationCache {
+    pub fn new(controller: &str, firmware: &str) -> Self {
+        Self {
+            controller: controller.to_string(),
+            firmware: firmware.to_string(),
+            language: None,
+            page_ids: BTreeMap::new(),
+        }
+    }
+
+    pub fn is_for(&self, controller: &str, firmware: &str) -> bool {
+        self.controller == controller && self.firmware == firmware
+    }
+}
+
+impl From<&NavigationCache> for Measurement {
+    // stored as a measurement with the page ids as sample values, so the state client can keep it
+    fn from(cache: &NavigationCache) -> Self {
+        Measurement {
+            id: cache.controller.clone(),
+            source: SOURCE.to_string(),
+            location: cache.firmware.clone(),
+            samples: cache
+                .page_ids
+                .iter()
+                .filter_map(|(navigation, id)| {
+                    Some(Sample {
+                        entity_type: EntityType::Invalid,
+                        // the language is kept as the name of its top level menu
+                        entity_name: cache
+                            .language
+                            .and_then(|l| l.translate("Information"))
+                            .unwrap_or_default()
+                            .to_string(),
+                        sample_type: SampleType::Invalid,
+                        sample_name: navigation.clone(),
+                        metric_type: MetricType::Invalid,
+                        value: u32::from_str_radix(id.trim_start_matches("0x"), 16).ok()? as f64,
+                    })
+                })
+                .collect(),
+            measured_at_time: Utc::now(),
+        }
+    }
+}
+
+impl From<&Measurement> for NavigationCache {
+    fn from(measurement: &Measurement) -> Self {
+        NavigationCache {
+            controller: measurement.id.clone(),
+            firmware: measurement.location.clone(),
+            language: measurement
+                .samples
+                .first()
+                .and_then(|s| Language::from_menu_name(&s.entity_name)),
+            page_ids: measurement
+                .samples
+                .iter()
+                .map(|s| (s.sample_name.clone(), format!("0x{:x}", s.value as u32)))
+                .collect(),
+        }
+    }
+}
+
+pub struct NavigationCacheClientConfig {
+    state_client_config: StateClientConfig,
+}
+
+impl NavigationCacheClientConfig {
+    pub async fn new(
+        navigation_cache_file_path: String,
+        navigation_cache_config_map_name: String,
+    ) -> Result<Self, Box<dyn Error>> {
+        debug!(
+            "NavigationCacheClientConfig::new(navigation_cache_file_path: {}, navigation_cache_config_map_name: {})",
+            navigation_cache_file_path, navigation_cache_config_map_name
+        );
+
+        Ok(Self {
+            state_client_config: StateClientConfig::new(
+                navigation_cache_file_path,
+                navigation_cache_config_map_name,
+            )
+            .await?,
+        })
+    }
+
+    pub async fn from_env() -> Result<Option<Self>, Box<dyn Error>> {
+        match env::var("NAVIGATION_CACHE_CONFIG_MAP_NAME") {
+            Ok(config_map_name) if !config_map_name.is_empty() => {
+                let file_path = env::var("NAVIGATION_CACHE_FILE_PATH")
+                    .unwrap_or_else(|_| "/navigation-cache/last-measurement.yaml".to_string());
+
+                Ok(Some(Self::new(file_path, config_map_name).await?))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct NavigationCacheClient {
+    state_client: Arc<StateClient>,
+}
+
+impl NavigationCacheClient {
+    pub fn new(config: NavigationCacheClientConfig) -> Self {
+        Self {
+            state_client: Arc::new(StateClient::new(config.state_client_config)),
+        }
+    }
+
+    async fn read_state(&self) -> Result<Vec<Measurement>, Box<dyn Error>> {
+        Ok(self
+            .state_client
+            .read_state()
+            .await?
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|m| m.source == SOURCE)
+            .collect())
+    }
+
+    pub async fn read_cache(
+        &self,
+        controller: &str,
+    ) -> Result<Option<NavigationCache>, Box<dyn Error>> {
+        Ok(self
+            .read_state()
+            .await?
+            .iter()
+            .find(|m| m.id == controller)
+            .map(NavigationCache::from))
+    }
+
+    // stores the caches of all controllers at once, keeping the stored caches of the other controllers
+    pub async fn store_caches(&self, caches: &[NavigationCache]) -> Result<(), Box<dyn Error>> {
+        if caches.is_empty() {
+            return Ok(());
+        }
+
+        let mut state = match self.read_state().await {
+            Ok(state) => state,
+            Err(e) => {
+                warn!(
+                    "Reading navigation cache failed, only storing the new caches: {}",
+                    e
+                );
+                vec![]
+            }
+        };
+        state.retain(|m| !caches.iter().any(|c| c.controller == m.id));
+        state.extend(caches.iter().map(Measurement::from));
+
+        self.state_client.store_state(&state).await?;
+
+        info!(
+            "Stored {} page ids in navigation cache",
+            caches.iter().map(|c| c.page_ids.len()).sum::<usize>()
+        );
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_for_requires_same_controller_and_firmware() {
+        let cache = NavigationCache::new("192.168.195.4:8214", "0x45cd88");
+
+        assert!(cache.is_for("192.168.195.4:8214", "0x45cd88"));
+        assert!(!cache.is_for("192.168.195.4:8214", "0x45cd90"));
+        assert!(!cache.is_for("192.168.195.5:8214", "0x45cd88"));
+    }
+
+    #[test]
+    fn navigation_cache_survives_conversion_to_measurement() {
+        let mut cache = NavigationCache::new("192.168.195.4:8214", "0x45cd88");
+        cache.language = Some(Language::Nl);
+        cache.page_ids.insert(
+            "Informatie > Temperaturen".to_string(),
+            "0x45df90".to_string(),
+        );
+        cache
+            .page_ids
+            .insert("Informatie > Ingangen".to_string(), "0x455968".to_string());
+
+        // act
+        let measurement = Measurement::from(&cache);
+
+        assert_eq!(measurement.samples.len(), 2);
+        assert_eq!(NavigationCache::from(&measurement), cache);
+    }
+}
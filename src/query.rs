@@ -0,0 +1,105 @@
+use crate::content::ContentItem;
+use crate::language::Language;
+use crate::table;
+use serde::Serialize;
+use std::fmt;
+
+// a single item as read by the get subcommand, without a sample config to convert it
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemValue {
+    pub navigation: String,
+    pub item: String,
+    pub item_id: String,
+    // the value as the controller shows it, before parsing
+    pub raw_value: String,
+    // none for states such as Aan or Uit and for items without a value
+    pub value: Option<f64>,
+    // unit of the parsed value, seconds for durations
+    pub unit: String,
+}
+
+impl ItemValue {
+    pub fn new(navigation: &str, item: &ContentItem, language: Option<Language>) -> Self {
+        Self {
+            navigation: navigation.to_string(),
+            item: item.name.clone(),
+            item_id: item.id.clone(),
+            raw_value: item.text.clone(),
+            value: if item.is_placeholder() {
+                None
+            } else {
+                item.value(None, language).ok()
+            },
+            unit: item.unit.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ItemValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rows = vec![
+            vec![
+                "item".to_string(),
+                format!("{} > {} ({})", self.navigation, self.item, self.item_id),
+            ],
+            vec!["raw".to_string(), self.raw_value.clone()],
+            vec![
+                "value".to_string(),
+                self.value.map_or("-".to_string(), |v| v.to_string()),
+            ],
+            vec!["unit".to_string(), self.unit.clone()],
+        ];
+
+        write!(f, "{}", table::render(&rows))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::ContentPage;
+
+    const BEDRIJFSUREN_RESPONSE: &str = "<Content><item id='0x4f3a2c'><name>Bedrijfsuren VD1</name><value>12345h</value></item><item id='0x4ea5ac'><name>Bedrijfsuren WP</name><value>12:30:00</value></item><item id='0x4e2c6c'><name>Status</name><value>Aan</value></item><name>Bedrijfsuren</name></Content>";
+
+    #[test]
+    fn new_parses_value_and_keeps_raw_value_and_unit() {
+        let page = ContentPage::from_response(BEDRIJFSUREN_RESPONSE).unwrap();
+
+        // act
+        let hours = ItemValue::new("Informatie > Bedrijfsuren", &page.items[0], None);
+        let duration = ItemValue::new("Informatie > Bedrijfsuren", &page.items[1], None);
+        let state = ItemValue::new("Informatie > Bedrijfsuren", &page.items[2], None);
+
+        assert_eq!(
+            hours,
+            ItemValue {
+                navigation: "Informatie > Bedrijfsuren".to_string(),
+                item: "Bedrijfsuren VD1".to_string(),
+                item_id: "0x4f3a2c".to_string(),
+                raw_value: "12345h".to_string(),
+                value: Some(12345.0),
+                unit: "h".to_string(),
+            }
+        );
+        assert_eq!(duration.raw_value, "12:30:00".to_string());
+        assert_eq!(duration.value, Some(45000.0));
+        assert_eq!(duration.unit, "s".to_string());
+        assert_eq!(state.value, None);
+        assert_eq!(state.raw_value, "Aan".to_string());
+    }
+
+    #[test]
+    fn serialize_item_value_as_json() {
+        let page = ContentPage::from_response(BEDRIJFSUREN_RESPONSE).unwrap();
+        let item_value = ItemValue::new("Informatie > Bedrijfsuren", &page.items[2], None);
+
+        // act
+        let json = serde_json::to_string(&item_value).unwrap();
+
+        assert_eq!(
+            json,
+            "{\"navigation\":\"Informatie > Bedrijfsuren\",\"item\":\"Status\",\"itemId\":\"0x4e2c6c\",\"rawValue\":\"Aan\",\"value\":null,\"unit\":\"\"}"
+        );
+    }
+}
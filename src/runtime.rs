@@ -0,0 +1,36 @@
+use std::future::Future;
+use tokio::runtime::{Handle, RuntimeFlavor};
+
+// runs a future to completion from the synchronous MeasurementClient interface, which is called from within the
+// tokio runtime main starts; block_in_place moves the other tasks off this worker thread so they don't starve,
+// which is only possible on the multi-threaded runtime, the current-thread runtime would panic or deadlock
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let handle = Handle::current();
+    assert_eq!(
+        handle.runtime_flavor(),
+        RuntimeFlavor::MultiThread,
+        "blocking on a future requires the multi-threaded tokio runtime"
+    );
+
+    tokio::task::block_in_place(|| handle.block_on(future))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn block_on_runs_future_on_multi_threaded_runtime() {
+        // act
+        let value = block_on(async { 42 });
+
+        assert_eq!(value, 42);
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "multi-threaded tokio runtime")]
+    async fn block_on_panics_on_current_thread_runtime() {
+        // act
+        block_on(async {});
+    }
+}
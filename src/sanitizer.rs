@@ -0,0 +1,36 @@
+use jarvis_lib::model::{MetricType, Sample};
+use tracing::info;
+
+pub fn sanitize_samples(current_samples: Vec<Sample>, last_samples: &[Sample]) -> Vec<Sample> {
+    let mut sanitized_samples: Vec<Sample> = Vec::new();
+
+    for current_sample in current_samples.into_iter() {
+        // check if there's a corresponding sample in lastSamples and see if the difference with it's value isn't too large
+        let mut sanitize = false;
+        for last_sample in last_samples.iter() {
+            if current_sample.entity_type == last_sample.entity_type
+                && current_sample.entity_name == last_sample.entity_name
+                && current_sample.sample_type == last_sample.sample_type
+                && current_sample.sample_name == last_sample.sample_name
+                && current_sample.metric_type == last_sample.metric_type
+            {
+                if current_sample.metric_type == MetricType::Counter
+                    && last_sample.value > 0.0
+                    && current_sample.value / last_sample.value > 1.1
+                {
+                    sanitize = true;
+                    info!("Value for {} is more than 10 percent larger than the last sampled value {}, keeping previous value instead", current_sample.sample_name, last_sample.value);
+                    sanitized_samples.push(last_sample.clone());
+                }
+
+                break;
+            }
+        }
+
+        if !sanitize {
+            sanitized_samples.push(current_sample);
+        }
+    }
+
+    sanitized_samples
+}
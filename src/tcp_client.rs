@@ -0,0 +1,474 @@
+use crate::discovery::DEFAULT_ENTITY_NAME;
+use crate::failure::{
+    check_failed_samples, failed_samples_sample, record_failed_samples, FailedSample,
+};
+use crate::model::{Config, ConfigSample, Transport};
+use crate::sanitizer::sanitize_samples;
+use chrono::Utc;
+use jarvis_lib::measurement_client::MeasurementClient;
+use jarvis_lib::model::{Measurement, Sample};
+use std::error::Error;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+use tracing::{debug, info};
+use uuid::Uuid;
+
+const READ_PARAMETERS_COMMAND: i32 = 3003;
+const READ_CALCULATIONS_COMMAND: i32 = 3004;
+const READ_VISIBILITIES_COMMAND: i32 = 3005;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(10);
+// the tables hold a few thousand values at most, a larger length means a corrupt response
+const MAX_TABLE_LENGTH: usize = 65_536;
+
+#[derive(Debug)]
+pub struct TcpClientConfig {
+    host_address: String,
+    host_port: u32,
+}
+
+impl TcpClientConfig {
+    pub fn new(host_address: String, host_port: u32) -> Result<Self, Box<dyn Error>> {
+        let config = Self {
+            host_address,
+            host_port,
+        };
+
+        debug!("{:?}", config);
+
+        Ok(config)
+    }
+}
+
+pub struct TcpClient {
+    config: TcpClientConfig,
+}
+
+impl MeasurementClient<Config> for TcpClient {
+    fn get_measurements(
+        &self,
+        config: Config,
+        last_measurement: Option<Vec<Measurement>>,
+    ) -> Result<Vec<Measurement>, Box<dyn Error>> {
+        config.check_transport(Transport::Tcp)?;
+
+        info!("Reading measurements from alpha innotec heatpump over luxtronik tcp protocol...");
+
+        let mut measurement = Measurement {
+            id: Uuid::new_v4().to_string(),
+            source: String::from("jarvis-alpha-innotec-exporter"),
+            location: config.location.clone(),
+            samples: Vec::new(),
+            measured_at_time: Utc::now(),
+        };
+
+        let mut stream = self.connect()?;
+
+        let data = self.read_data(&mut stream, &config.sample_configs)?;
+
+        let (samples, failed_samples) = self.get_samples(&config.sample_configs, &data)?;
+
+        check_failed_samples(
+            &failed_samples,
+            config.sample_configs.len(),
+            config.max_failed_samples,
+        )?;
+        measurement.samples = samples;
+
+        if config.sanitize_samples {
+            if let Some(lm) = last_measurement {
+                if !lm.is_empty() {
+                    measurement.samples =
+                        sanitize_samples(measurement.samples, &lm[lm.len() - 1].samples)
+                }
+            }
+        }
+
+        if config.publish_failed_samples {
+            let entity_name = config
+                .sample_configs
+                .first()
+                .map(|sc| sc.entity_name.clone())
+                .unwrap_or_else(|| DEFAULT_ENTITY_NAME.to_string());
+            measurement
+                .samples
+                .push(failed_samples_sample(&entity_name, &failed_samples));
+        }
+
+        info!("Read measurement from alpha innotec heatpump");
+
+        Ok(vec![measurement])
+    }
+}
+
+#[derive(Debug, Default)]
+struct LuxtronikData {
+    parameters: Vec<i32>,
+    calculations: Vec<i32>,
+    visibilities: Vec<i8>,
+}
+
+impl TcpClient {
+    pub fn new(config: TcpClientConfig) -> Self {
+        Self { config }
+    }
+
+    // a controller that doesn't respond fails the scrape instead of hanging the exporter
+    fn connect(&self) -> Result<TcpStream, Box<dyn Error>> {
+        let address = format!("{}:{}", self.config.host_address, self.config.host_port);
+
+        let mut last_error = None;
+        for socket_address in address.to_socket_addrs()? {
+            match TcpStream::connect_timeout(&socket_address, CONNECT_TIMEOUT) {
+                Ok(stream) => {
+                    stream.set_read_timeout(Some(RESPONSE_TIMEOUT))?;
+                    stream.set_write_timeout(Some(RESPONSE_TIMEOUT))?;
+                    return Ok(stream);
+                }
+                Err(e) => last_error = Some(e),
+            }
+        }
+
+        Err(match last_error {
+            Some(e) => Box::<dyn Error>::from(format!("Connecting to {} failed: {}", address, e)),
+            None => Box::<dyn Error>::from(format!("Address {} didn't resolve", address)),
+        })
+    }
+
+    fn read_data<S: Read + Write>(
+        &self,
+        stream: &mut S,
+        sample_configs: &[ConfigSample],
+    ) -> Result<LuxtronikData, Box<dyn Error>> {
+        let mut data = LuxtronikData::default();
+
+        // only request the tables that are actually referenced by a sample
+        if sample_configs.iter().any(|sc| sc.parameter.is_some()) {
+            data.parameters = self.read_parameters(stream)?;
+        }
+        if sample_configs.iter().any(|sc| sc.calculation.is_some()) {
+            data.calculations = self.read_calculations(stream)?;
+        }
+        if sample_configs.iter().any(|sc| sc.visibility.is_some()) {
+            data.visibilities = self.read_visibilities(stream)?;
+        }
+
+        Ok(data)
+    }
+
+    fn get_samples(
+        &self,
+        sample_configs: &[ConfigSample],
+        data: &LuxtronikData,
+    ) -> Result<(Vec<Sample>, Vec<FailedSample>), Box<dyn Error>> {
+        let mut samples = Vec::new();
+        let mut failed_samples = Vec::new();
+
+        for sample_config in sample_configs.iter() {
+            let value = match self.get_value(sample_config, data) {
+                Ok(value) => value,
+                Err(e) => {
+                    record_failed_samples(
+                        &mut failed_samples,
+                        std::slice::from_ref(sample_config),
+                        e,
+                    )?;
+                    continue;
+                }
+            };
+
+            samples.push(Sample {
+                entity_type: sample_config.entity_type,
+                entity_name: sample_config.entity_name.clone(),
+                sample_type: sample_config.sample_type,
+                sample_name: sample_config.sample_name.clone(),
+                metric_type: sample_config.metric_type,
+                value: value * sample_config.value_multiplier,
+            });
+        }
+
+        Ok((samples, failed_samples))
+    }
+
+    fn get_value(
+        &self,
+        sample_config: &ConfigSample,
+        data: &LuxtronikData,
+    ) -> Result<f64, Box<dyn Error>> {
+        let (table, index, value) = if let Some(index) = sample_config.calculation {
+            (
+                "calculation",
+                index,
+                data.calculations.get(index).map(|v| *v as f64),
+            )
+        } else if let Some(index) = sample_config.parameter {
+            (
+                "parameter",
+                index,
+                data.parameters.get(index).map(|v| *v as f64),
+            )
+        } else if let Some(index) = sample_config.visibility {
+            (
+                "visibility",
+                index,
+                data.visibilities.get(index).map(|v| *v as f64),
+            )
+        } else {
+            return Err(Box::<dyn Error>::from(format!(
+                "Sample {} has no calculation, parameter or visibility index",
+                sample_config.sample_name
+            )));
+        };
+
+        match value {
+            Some(v) => Ok(v),
+            None => Err(Box::<dyn Error>::from(format!(
+                "No {} with index {} for sample {}",
+                table, index, sample_config.sample_name
+            ))),
+        }
+    }
+
+    fn read_parameters<S: Read + Write>(&self, stream: &mut S) -> Result<Vec<i32>, Box<dyn Error>> {
+        self.send_command(stream, READ_PARAMETERS_COMMAND)?;
+        self.expect_command(stream, READ_PARAMETERS_COMMAND)?;
+
+        let length = self.read_length(stream)?;
+        (0..length).map(|_| self.read_i32(stream)).collect()
+    }
+
+    fn read_calculations<S: Read + Write>(
+        &self,
+        stream: &mut S,
+    ) -> Result<Vec<i32>, Box<dyn Error>> {
+        self.send_command(stream, READ_CALCULATIONS_COMMAND)?;
+        self.expect_command(stream, READ_CALCULATIONS_COMMAND)?;
+
+        // calculations are preceded by a status word
+        let _status = self.read_i32(stream)?;
+        let length = self.read_length(stream)?;
+        (0..length).map(|_| self.read_i32(stream)).collect()
+    }
+
+    fn read_visibilities<S: Read + Write>(
+        &self,
+        stream: &mut S,
+    ) -> Result<Vec<i8>, Box<dyn Error>> {
+        self.send_command(stream, READ_VISIBILITIES_COMMAND)?;
+        self.expect_command(stream, READ_VISIBILITIES_COMMAND)?;
+
+        let length = self.read_length(stream)?;
+        let mut buffer = vec![0u8; length];
+        stream.read_exact(&mut buffer)?;
+
+        Ok(buffer.into_iter().map(|b| b as i8).collect())
+    }
+
+    fn send_command<S: Write>(&self, stream: &mut S, command: i32) -> Result<(), Box<dyn Error>> {
+        let mut message = Vec::with_capacity(8);
+        message.extend_from_slice(&command.to_be_bytes());
+        message.extend_from_slice(&0i32.to_be_bytes());
+
+        stream.write_all(&message)?;
+        stream.flush()?;
+
+        Ok(())
+    }
+
+    fn expect_command<S: Read>(&self, stream: &mut S, command: i32) -> Result<(), Box<dyn Error>> {
+        let echoed_command = self.read_i32(stream)?;
+        if echoed_command != command {
+            return Err(Box::<dyn Error>::from(format!(
+                "Expected response for command {}, got {}",
+                command, echoed_command
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn read_length<S: Read>(&self, stream: &mut S) -> Result<usize, Box<dyn Error>> {
+        let length = self.read_i32(stream)?;
+        if length < 0 || length as usize > MAX_TABLE_LENGTH {
+            return Err(Box::<dyn Error>::from(format!(
+                "Invalid response length {}",
+                length
+            )));
+        }
+
+        Ok(length as usize)
+    }
+
+    fn read_i32<S: Read>(&self, stream: &mut S) -> Result<i32, Box<dyn Error>> {
+        let mut buffer = [0u8; 4];
+        stream.read_exact(&mut buffer)?;
+
+        Ok(i32::from_be_bytes(buffer))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jarvis_lib::model::SampleType;
+    use std::io::Cursor;
+
+    // in-memory stand-in for the heatpump socket, recording what was sent and replaying a canned response
+    struct FakeStream {
+        sent: Vec<u8>,
+        response: Cursor<Vec<u8>>,
+    }
+
+    impl FakeStream {
+        fn new(words: &[i32]) -> Self {
+            let mut response = Vec::new();
+            for word in words.iter() {
+                response.extend_from_slice(&word.to_be_bytes());
+            }
+
+            Self {
+                sent: Vec::new(),
+                response: Cursor::new(response),
+            }
+        }
+    }
+
+    impl Read for FakeStream {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.response.read(buf)
+        }
+    }
+
+    impl Write for FakeStream {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.sent.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn sample_config(calculation: Option<usize>, parameter: Option<usize>) -> ConfigSample {
+        ConfigSample {
+            value_multiplier: 0.1,
+            calculation,
+            parameter,
+            ..ConfigSample::for_test(SampleType::Temperature, "Aanvoer", "", "")
+        }
+    }
+
+    #[test]
+    fn read_calculations_sends_command_and_returns_values() {
+        let tcp_client =
+            TcpClient::new(TcpClientConfig::new("192.168.178.94".to_string(), 8889).unwrap());
+        let mut stream = FakeStream::new(&[3004, 0, 3, 223, 220, -12]);
+
+        // act
+        let calculations = tcp_client.read_calculations(&mut stream).unwrap();
+
+        assert_eq!(stream.sent, vec![0, 0, 11, 188, 0, 0, 0, 0]);
+        assert_eq!(calculations, vec![223, 220, -12]);
+    }
+
+    #[test]
+    fn read_parameters_returns_error_if_response_is_for_other_command() {
+        let tcp_client =
+            TcpClient::new(TcpClientConfig::new("192.168.178.94".to_string(), 8889).unwrap());
+        let mut stream = FakeStream::new(&[3004, 0, 0]);
+
+        // act
+        let result = tcp_client.read_parameters(&mut stream);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn read_visibilities_returns_error_for_length_beyond_maximum() {
+        let tcp_client =
+            TcpClient::new(TcpClientConfig::new("192.168.178.94".to_string(), 8889).unwrap());
+        let mut stream = FakeStream::new(&[3005, i32::MAX]);
+
+        // act
+        let result = tcp_client.read_visibilities(&mut stream);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn read_data_only_requests_referenced_tables() {
+        let tcp_client =
+            TcpClient::new(TcpClientConfig::new("192.168.178.94".to_string(), 8889).unwrap());
+        let mut stream = FakeStream::new(&[3004, 0, 2, 223, 220]);
+
+        // act
+        let data = tcp_client
+            .read_data(&mut stream, &[sample_config(Some(10), None)])
+            .unwrap();
+
+        assert_eq!(data.calculations, vec![223, 220]);
+        assert!(data.parameters.is_empty());
+        assert!(data.visibilities.is_empty());
+    }
+
+    #[test]
+    fn get_samples_applies_value_multiplier_to_indexed_calculation() {
+        let tcp_client =
+            TcpClient::new(TcpClientConfig::new("192.168.178.94".to_string(), 8889).unwrap());
+        let data = LuxtronikData {
+            parameters: vec![],
+            calculations: vec![223, 220],
+            visibilities: vec![],
+        };
+
+        // act
+        let (samples, _) = tcp_client
+            .get_samples(&[sample_config(Some(1), None)], &data)
+            .unwrap();
+
+        assert_eq!(samples.len(), 1);
+        assert_eq!(samples[0].value, 22.0);
+    }
+
+    #[test]
+    fn get_samples_returns_failed_sample_if_index_is_out_of_range() {
+        let tcp_client =
+            TcpClient::new(TcpClientConfig::new("192.168.178.94".to_string(), 8889).unwrap());
+        let data = LuxtronikData {
+            parameters: vec![],
+            calculations: vec![223, 220],
+            visibilities: vec![],
+        };
+
+        // act
+        let (samples, failed_samples) = tcp_client
+            .get_samples(
+                &[sample_config(Some(0), None), sample_config(None, Some(3))],
+                &data,
+            )
+            .unwrap();
+
+        assert_eq!(samples.len(), 1);
+        assert_eq!(failed_samples.len(), 1);
+        assert_eq!(
+            failed_samples[0].reason,
+            "No parameter with index 3 for sample Aanvoer".to_string()
+        );
+    }
+
+    #[test]
+    fn get_samples_returns_error_if_index_is_out_of_range_for_required_sample() {
+        let tcp_client =
+            TcpClient::new(TcpClientConfig::new("192.168.178.94".to_string(), 8889).unwrap());
+        let data = LuxtronikData::default();
+        let mut sample_config = sample_config(None, Some(3));
+        sample_config.required = true;
+
+        // act
+        let result = tcp_client.get_samples(&[sample_config], &data);
+
+        assert!(result.is_err());
+    }
+}
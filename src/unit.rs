@@ -0,0 +1,233 @@
+use jarvis_lib::model::SampleType;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(from = "String", into = "String")]
+pub enum Unit {
+    None,
+    DegreesCelsius,
+    Kelvin,
+    Bar,
+    LitersPerHour,
+    Volt,
+    KilowattHour,
+    Hour,
+    Minute,
+    Second,
+    Percent,
+    Other(String),
+}
+
+impl Unit {
+    // converts a value in this unit to the si unit jarvis stores for the sample type
+    pub fn to_si(&self, value: f64, sample_type: SampleType) -> Result<f64, Box<dyn Error>> {
+        Ok(value * self.si_factor(sample_type)?)
+    }
+
+    // configs from before the automatic conversion scale with a valueMultiplier equal to the si factor, such as
+    // 3600000 for kWh; applying both would export values that many times too large
+    pub fn is_legacy_multiplier(&self, sample_type: SampleType, value_multiplier: f64) -> bool {
+        match self.si_factor(sample_type) {
+            Ok(factor) => factor != 1.0 && (value_multiplier - factor).abs() <= factor.abs() * 1e-9,
+            Err(_) => false,
+        }
+    }
+
+    fn si_factor(&self, sample_type: SampleType) -> Result<f64, Box<dyn Error>> {
+        let factor = match (sample_type, self) {
+            (_, Unit::None) => Some(1.0),
+            (SampleType::Temperature, Unit::DegreesCelsius) => Some(1.0),
+            // kelvin only shows up for temperature differences, which are the same in °C
+            (SampleType::Temperature, Unit::Kelvin) => Some(1.0),
+            (SampleType::Pressure, Unit::Bar) => Some(100000.0),
+            (SampleType::Flow, Unit::LitersPerHour) => Some(1.0 / 3600000.0),
+            (SampleType::Energy, Unit::KilowattHour) => Some(3600000.0),
+            (SampleType::Time, Unit::Hour) => Some(3600.0),
+            (SampleType::Time, Unit::Minute) => Some(60.0),
+            (SampleType::Time, Unit::Second) => Some(1.0),
+            (SampleType::Temperature, _)
+            | (SampleType::Pressure, _)
+            | (SampleType::Flow, _)
+            | (SampleType::Energy, _)
+            | (SampleType::Time, _) => None,
+            // no known conversion for other sample types, pass the value on as is
+            (_, _) => Some(1.0),
+        };
+
+        match factor {
+            Some(factor) => Ok(factor),
+            None => Err(Box::<dyn Error>::from(format!(
+                "Unit {} is incompatible with sample type {:?}",
+                self, sample_type
+            ))),
+        }
+    }
+
+    // the sample type a value in this unit most likely represents, used to prefill discovered samples
+    pub fn sample_type(&self) -> SampleType {
+        match self {
+            Unit::DegreesCelsius | Unit::Kelvin => SampleType::Temperature,
+            Unit::Bar => SampleType::Pressure,
+            Unit::LitersPerHour => SampleType::Flow,
+            Unit::KilowattHour => SampleType::Energy,
+            Unit::Hour | Unit::Minute | Unit::Second => SampleType::Time,
+            _ => SampleType::Invalid,
+        }
+    }
+}
+
+impl From<&str> for Unit {
+    fn from(s: &str) -> Self {
+        match s.trim() {
+            "" => Unit::None,
+            "°C" => Unit::DegreesCelsius,
+            "K" => Unit::Kelvin,
+            "bar" => Unit::Bar,
+            "l/h" => Unit::LitersPerHour,
+            "V" => Unit::Volt,
+            "kWh" => Unit::KilowattHour,
+            "h" => Unit::Hour,
+            "min" => Unit::Minute,
+            "s" => Unit::Second,
+            "%" => Unit::Percent,
+            other => Unit::Other(other.to_string()),
+        }
+    }
+}
+
+impl From<String> for Unit {
+    fn from(s: String) -> Self {
+        Unit::from(s.as_str())
+    }
+}
+
+impl FromStr for Unit {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Unit::from(s))
+    }
+}
+
+impl From<Unit> for String {
+    fn from(unit: Unit) -> Self {
+        unit.to_string()
+    }
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Unit::None => "",
+            Unit::DegreesCelsius => "°C",
+            Unit::Kelvin => "K",
+            Unit::Bar => "bar",
+            Unit::LitersPerHour => "l/h",
+            Unit::Volt => "V",
+            Unit::KilowattHour => "kWh",
+            Unit::Hour => "h",
+            Unit::Minute => "min",
+            Unit::Second => "s",
+            Unit::Percent => "%",
+            Unit::Other(other) => other,
+        };
+
+        write!(f, "{}", s)
+    }
+}
+
+// the unit the controller showed for each sample whose value was converted to si, by entity and sample name; tcp
+// samples and items without a unit aren't in here, as their unit is unknown
+#[derive(Clone, Default)]
+pub struct SampleUnits {
+    units: Arc<Mutex<HashMap<(String, String), Unit>>>,
+}
+
+impl SampleUnits {
+    pub fn set(&self, entity_name: &str, sample_name: &str, unit: Option<Unit>) {
+        let key = (entity_name.to_string(), sample_name.to_string());
+        let mut units = self.units.lock().unwrap();
+        match unit {
+            Some(unit) => units.insert(key, unit),
+            None => units.remove(&key),
+        };
+    }
+
+    pub fn get(&self, entity_name: &str, sample_name: &str) -> Option<Unit> {
+        self.units
+            .lock()
+            .unwrap()
+            .get(&(entity_name.to_string(), sample_name.to_string()))
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_str_parses_luxtronik_units() {
+        assert_eq!("°C".parse::<Unit>().unwrap(), Unit::DegreesCelsius);
+        assert_eq!("l/h".parse::<Unit>().unwrap(), Unit::LitersPerHour);
+        assert_eq!("kWh".parse::<Unit>().unwrap(), Unit::KilowattHour);
+        assert_eq!("".parse::<Unit>().unwrap(), Unit::None);
+        assert_eq!("Hz".parse::<Unit>().unwrap(), Unit::Other("Hz".to_string()));
+    }
+
+    #[test]
+    fn to_si_converts_to_unit_matching_sample_type() {
+        assert_eq!(
+            Unit::Bar.to_si(8.1, SampleType::Pressure).unwrap(),
+            810000.0
+        );
+        assert_eq!(
+            Unit::LitersPerHour.to_si(1800.0, SampleType::Flow).unwrap(),
+            0.0005
+        );
+        assert_eq!(
+            Unit::KilowattHour.to_si(2.5, SampleType::Energy).unwrap(),
+            9000000.0
+        );
+        assert_eq!(Unit::Hour.to_si(2.0, SampleType::Time).unwrap(), 7200.0);
+        assert_eq!(
+            Unit::DegreesCelsius
+                .to_si(22.3, SampleType::Temperature)
+                .unwrap(),
+            22.3
+        );
+    }
+
+    #[test]
+    fn is_legacy_multiplier_returns_true_for_multiplier_equal_to_si_factor() {
+        assert!(Unit::KilowattHour.is_legacy_multiplier(SampleType::Energy, 3600000.0));
+        assert!(Unit::Bar.is_legacy_multiplier(SampleType::Pressure, 100000.0));
+        assert!(Unit::Hour.is_legacy_multiplier(SampleType::Time, 3600.0));
+        assert!(Unit::Minute.is_legacy_multiplier(SampleType::Time, 60.0));
+        assert!(!Unit::KilowattHour.is_legacy_multiplier(SampleType::Energy, 1.0));
+        assert!(!Unit::DegreesCelsius.is_legacy_multiplier(SampleType::Temperature, 1.0));
+        assert!(!Unit::None.is_legacy_multiplier(SampleType::Energy, 3600000.0));
+    }
+
+    #[test]
+    fn sample_type_returns_sample_type_matching_unit() {
+        assert_eq!(Unit::DegreesCelsius.sample_type(), SampleType::Temperature);
+        assert_eq!(Unit::Bar.sample_type(), SampleType::Pressure);
+        assert_eq!(Unit::Second.sample_type(), SampleType::Time);
+        assert_eq!(Unit::Volt.sample_type(), SampleType::Invalid);
+    }
+
+    #[test]
+    fn to_si_returns_error_for_unit_incompatible_with_sample_type() {
+        assert!(Unit::Bar.to_si(8.1, SampleType::Temperature).is_err());
+        assert!(Unit::DegreesCelsius
+            .to_si(22.3, SampleType::Energy)
+            .is_err());
+    }
+}
@@ -0,0 +1,228 @@
+use crate::content::ContentPage;
+use crate::language::Language;
+use crate::model::ConfigSample;
+use std::fmt;
+
+const MAX_SUGGESTIONS: usize = 3;
+
+#[derive(Debug, PartialEq)]
+pub enum ValidationResult {
+    Pass,
+    // usable, but likely not what was intended
+    Warn(String),
+    Fail(String, Vec<String>),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct SampleValidation {
+    pub sample_name: String,
+    pub navigation: String,
+    pub item: String,
+    pub result: ValidationResult,
+}
+
+impl SampleValidation {
+    pub fn new(sample_config: &ConfigSample, result: ValidationResult) -> Self {
+        Self {
+            sample_name: sample_config.sample_name.clone(),
+            navigation: sample_config.navigation.clone(),
+            item: sample_config.item.clone(),
+            result,
+        }
+    }
+
+    pub fn passed(&self) -> bool {
+        self.result == ValidationResult::Pass
+    }
+
+    pub fn failed(&self) -> bool {
+        matches!(self.result, ValidationResult::Fail(_, _))
+    }
+}
+
+impl fmt::Display for SampleValidation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let subject = format!("{} ({} > {})", self.sample_name, self.navigation, self.item);
+
+        match &self.result {
+            ValidationResult::Pass => write!(f, "PASS {}", subject),
+            ValidationResult::Warn(message) => write!(f, "WARN {}: {}", subject, message),
+            ValidationResult::Fail(message, suggestions) if suggestions.is_empty() => {
+                write!(f, "FAIL {}: {}", subject, message)
+            }
+            ValidationResult::Fail(message, suggestions) => write!(
+                f,
+                "FAIL {}: {}, did you mean {}?",
+                subject,
+                message,
+                suggestions.join(" or ")
+            ),
+        }
+    }
+}
+
+// checks that the configured item exists on the page and its value converts to the configured sample type
+pub fn validate_sample(
+    sample_config: &ConfigSample,
+    page: &ContentPage,
+    language: Option<Language>,
+) -> ValidationResult {
+    let item = match page.select_item(sample_config, language) {
+        Ok(item) => item,
+        Err(e) => {
+            let names: Vec<&str> = page.items.iter().map(|item| item.name.as_str()).collect();
+            return ValidationResult::Fail(e.to_string(), suggest(&sample_config.item, &names));
+        }
+    };
+
+    if item.is_placeholder() {
+        return ValidationResult::Warn(format!(
+            "Item {} has no value available, it's exported according to missingValue",
+            item.name
+        ));
+    }
+
+    let value = match item.value(sample_config.value_mapping.as_ref(), language) {
+        Ok(value) => value,
+        Err(e) => return ValidationResult::Fail(e.to_string(), vec![]),
+    };
+
+    if let Err(e) = item.unit.to_si(value, sample_config.sample_type) {
+        return ValidationResult::Fail(e.to_string(), vec![]);
+    }
+
+    if item
+        .unit
+        .is_legacy_multiplier(sample_config.sample_type, sample_config.value_multiplier)
+    {
+        return ValidationResult::Warn(format!(
+            "valueMultiplier {} converts {} to si itself, remove it as values are converted automatically",
+            sample_config.value_multiplier, item.unit
+        ));
+    }
+
+    if page.is_ambiguous(sample_config, language) {
+        return ValidationResult::Warn(format!(
+            "Item {} occurs more than once on the page, set itemId, itemOccurrence or itemUnit to select the right one",
+            sample_config.item
+        ));
+    }
+
+    ValidationResult::Pass
+}
+
+// the existing names closest to a misspelled one, best match first
+pub fn suggest(name: &str, candidates: &[&str]) -> Vec<String> {
+    let name = name.to_lowercase();
+    let max_distance = std::cmp::max(3, name.chars().count() / 3);
+
+    let mut scored: Vec<(usize, &str)> = candidates
+        .iter()
+        .map(|candidate| {
+            (
+                strsim::levenshtein(&name, &candidate.to_lowercase()),
+                *candidate,
+            )
+        })
+        .filter(|(distance, _)| *distance <= max_distance)
+        .collect();
+    scored.sort_by_key(|(distance, _)| *distance);
+
+    let mut suggestions: Vec<String> = Vec::new();
+    for (_, candidate) in scored.into_iter() {
+        if !suggestions.iter().any(|s| s == candidate) {
+            suggestions.push(candidate.to_string());
+        }
+    }
+    suggestions.truncate(MAX_SUGGESTIONS);
+
+    suggestions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::{BuiltinValueMapping, ValueMapping};
+    use jarvis_lib::model::SampleType;
+
+    const INGANGEN_RESPONSE: &str = "<Content><item id='0x4ffbfc'><name>EVU</name><value>Aan</value></item><item id='0x4ef3b4'><name>HD</name><value>Uit</value></item><item id='0x4e6a3c'><name>HD</name><value>8.10 bar</value></item><item id='0x4ca47c'><name>ND</name><value>---</value></item><item id='0x4e8004'><name>Debiet</name><value>1200 l/h</value></item><name>Ingangen</name></Content>";
+
+    fn sample_config(item: &str, sample_type: SampleType) -> ConfigSample {
+        ConfigSample::for_test(sample_type, "Debiet", "Informatie > Ingangen", item)
+    }
+
+    #[test]
+    fn validate_sample_passes_for_existing_item_with_matching_unit() {
+        let page = ContentPage::from_response(INGANGEN_RESPONSE).unwrap();
+
+        // act
+        let result = validate_sample(&sample_config("Debiet", SampleType::Flow), &page, None);
+
+        assert_eq!(result, ValidationResult::Pass);
+    }
+
+    #[test]
+    fn validate_sample_fails_with_suggestion_for_misspelled_item() {
+        let page = ContentPage::from_response(INGANGEN_RESPONSE).unwrap();
+
+        // act
+        let result = validate_sample(&sample_config("Debit", SampleType::Flow), &page, None);
+
+        assert_eq!(
+            result,
+            ValidationResult::Fail(
+                "No match for item Debit".to_string(),
+                vec!["Debiet".to_string()]
+            )
+        );
+    }
+
+    #[test]
+    fn validate_sample_fails_for_unit_incompatible_with_sample_type() {
+        let page = ContentPage::from_response(INGANGEN_RESPONSE).unwrap();
+
+        // act
+        let result = validate_sample(
+            &sample_config("Debiet", SampleType::Temperature),
+            &page,
+            None,
+        );
+
+        assert!(matches!(result, ValidationResult::Fail(_, _)));
+    }
+
+    #[test]
+    fn validate_sample_fails_for_state_text_without_value_mapping() {
+        let page = ContentPage::from_response(INGANGEN_RESPONSE).unwrap();
+        let mut sample_config = sample_config("EVU", SampleType::Invalid);
+
+        // act
+        let without_mapping = validate_sample(&sample_config, &page, None);
+        sample_config.value_mapping = Some(ValueMapping::Builtin(BuiltinValueMapping::OnOff));
+        let with_mapping = validate_sample(&sample_config, &page, None);
+
+        assert!(matches!(without_mapping, ValidationResult::Fail(_, _)));
+        assert_eq!(with_mapping, ValidationResult::Pass);
+    }
+
+    #[test]
+    fn validate_sample_warns_for_ambiguous_and_missing_values() {
+        let page = ContentPage::from_response(INGANGEN_RESPONSE).unwrap();
+
+        // act
+        let ambiguous = validate_sample(&sample_config("HD", SampleType::Pressure), &page, None);
+        let missing = validate_sample(&sample_config("ND", SampleType::Pressure), &page, None);
+
+        assert!(matches!(ambiguous, ValidationResult::Warn(_)));
+        assert!(matches!(missing, ValidationResult::Warn(_)));
+    }
+
+    #[test]
+    fn suggest_returns_closest_names_first() {
+        // act
+        let suggestions = suggest("Retour", &["Retour berekend", "Aanvoer", "Retur", "Retoer"]);
+
+        assert_eq!(suggestions, vec!["Retur".to_string(), "Retoer".to_string()]);
+        assert!(suggest("Buitentemperatuur", &["EVU", "HD"]).is_empty());
+    }
+}
@@ -0,0 +1,254 @@
+use crate::language::Language;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// state texts as shown by the luxtronik 2 controller in its different display languages
+const ON_TEXTS: &[&str] = &[
+    "Aan",       // nl
+    "Ein",       // de
+    "An",        // de
+    "On",        // en
+    "Marche",    // fr
+    "Acceso",    // it
+    "Encendido", // es
+    "Ligado",    // pt
+    "Til",       // sv, no, da
+    "På",        // sv, no, da
+    "Päällä",    // fi
+    "Zap",       // cs
+    "Zał",       // pl
+    "Be",        // hu
+];
+
+const OFF_TEXTS: &[&str] = &[
+    "Uit",       // nl
+    "Aus",       // de
+    "Off",       // en
+    "Arrêt",     // fr
+    "Spento",    // it
+    "Apagado",   // es
+    "Desligado", // pt
+    "Från",      // sv
+    "Fra",       // no, da
+    "Av",        // no
+    "Pois",      // fi
+    "Vyp",       // cs
+    "Wył",       // pl
+    "Ki",        // hu
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum ValueMapping {
+    Builtin(BuiltinValueMapping),
+    Custom(HashMap<String, f64>),
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum BuiltinValueMapping {
+    // on/off states in all luxtronik languages, mapped to 1 and 0
+    OnOff,
+}
+
+impl ValueMapping {
+    pub fn map(&self, raw_value: &str) -> Option<f64> {
+        let raw_value = normalize(raw_value);
+
+        match self {
+            ValueMapping::Builtin(BuiltinValueMapping::OnOff) => {
+                if contains_ignore_case(ON_TEXTS, raw_value) {
+                    Some(1.0)
+                } else if contains_ignore_case(OFF_TEXTS, raw_value) {
+                    Some(0.0)
+                } else {
+                    None
+                }
+            }
+            ValueMapping::Custom(map) => map
+                .iter()
+                .find(|(text, _)| normalize(text) == raw_value)
+                .or_else(|| {
+                    map.iter()
+                        .find(|(text, _)| normalize(text).eq_ignore_ascii_case(raw_value))
+                })
+                .map(|(_, value)| *value),
+        }
+    }
+}
+
+// the controller abbreviates some states with a trailing dot, such as Wył.
+fn normalize(text: &str) -> &str {
+    text.trim().trim_end_matches('.')
+}
+
+// parses numbers using the separators of the controller language, or guesses them when the language is unknown
+pub fn parse_number(raw_value: &str, language: Option<Language>) -> Option<f64> {
+    let raw_value: String = raw_value
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '\u{a0}' && *c != '\u{202f}')
+        .collect();
+
+    let (decimal_separator, thousands_separator) = match language {
+        Some(language) => (language.decimal_separator(), language.thousands_separator()),
+        None => guess_separators(&raw_value),
+    };
+
+    let mut normalized = raw_value;
+    if let Some(thousands_separator) = thousands_separator {
+        normalized = normalized.replace(thousands_separator, "");
+    }
+    if decimal_separator != '.' {
+        if normalized.contains('.') {
+            // a point that isn't the decimal or thousands separator makes the number ambiguous
+            return None;
+        }
+        normalized = normalized.replace(decimal_separator, ".");
+    }
+
+    normalized.parse().ok()
+}
+
+fn guess_separators(raw_value: &str) -> (char, Option<char>) {
+    let last_point = raw_value.rfind('.');
+    let last_comma = raw_value.rfind(',');
+
+    match (last_point, last_comma) {
+        // with both present the last one separates the decimals
+        (Some(p), Some(c)) if c > p => (',', Some('.')),
+        (Some(_), Some(_)) => ('.', Some(',')),
+        // a separator that occurs more than once can only group thousands
+        (None, Some(_)) if raw_value.matches(',').count() > 1 => ('.', Some(',')),
+        (Some(_), None) if raw_value.matches('.').count() > 1 => (',', Some('.')),
+        (None, Some(_)) => (',', None),
+        _ => ('.', None),
+    }
+}
+
+// parses h:mm and h:mm:ss durations as shown on the operating hours and timer pages into seconds
+pub fn parse_duration(raw_value: &str) -> Option<f64> {
+    let parts: Vec<&str> = raw_value.trim().split(':').collect();
+    if parts.len() < 2 || parts.len() > 3 {
+        return None;
+    }
+
+    let mut numbers = Vec::with_capacity(parts.len());
+    for part in parts.iter() {
+        if part.is_empty() || !part.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        numbers.push(part.parse::<u64>().ok()?);
+    }
+
+    // minutes and seconds can't overflow into the next unit
+    if numbers[1..].iter().any(|n| *n >= 60) {
+        return None;
+    }
+
+    let seconds = match numbers.as_slice() {
+        [hours, minutes] => hours * 3600 + minutes * 60,
+        [hours, minutes, seconds] => hours * 3600 + minutes * 60 + seconds,
+        _ => return None,
+    };
+
+    Some(seconds as f64)
+}
+
+fn contains_ignore_case(texts: &[&str], raw_value: &str) -> bool {
+    texts
+        .iter()
+        .any(|text| text.to_lowercase() == raw_value.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn map_returns_on_off_for_all_languages() {
+        let value_mapping = ValueMapping::Builtin(BuiltinValueMapping::OnOff);
+
+        assert_eq!(value_mapping.map("Aan"), Some(1.0));
+        assert_eq!(value_mapping.map("Uit"), Some(0.0));
+        assert_eq!(value_mapping.map("Ein"), Some(1.0));
+        assert_eq!(value_mapping.map("Aus"), Some(0.0));
+        assert_eq!(value_mapping.map("on"), Some(1.0));
+        assert_eq!(value_mapping.map("OFF"), Some(0.0));
+        assert_eq!(value_mapping.map("Arrêt"), Some(0.0));
+        assert_eq!(value_mapping.map("Wył."), Some(0.0));
+        assert_eq!(value_mapping.map("Verwarmen"), None);
+    }
+
+    #[test]
+    fn map_returns_value_from_custom_map() {
+        let mut map = HashMap::new();
+        map.insert("Verwarmen".to_string(), 1.0);
+        map.insert("Tapwater".to_string(), 2.0);
+        let value_mapping = ValueMapping::Custom(map);
+
+        assert_eq!(value_mapping.map("Tapwater"), Some(2.0));
+        assert_eq!(value_mapping.map("verwarmen"), Some(1.0));
+        assert_eq!(value_mapping.map("Koelen"), None);
+    }
+
+    #[test]
+    fn map_ignores_trailing_dot_in_custom_map_keys() {
+        let mut map = HashMap::new();
+        map.insert("Aus.".to_string(), 0.0);
+        let value_mapping = ValueMapping::Custom(map);
+
+        assert_eq!(value_mapping.map("Aus."), Some(0.0));
+        assert_eq!(value_mapping.map("Aus"), Some(0.0));
+    }
+
+    #[test]
+    fn parse_number_uses_separators_of_language() {
+        assert_eq!(parse_number("22,3", Some(Language::De)), Some(22.3));
+        assert_eq!(parse_number("12.345,6", Some(Language::De)), Some(12345.6));
+        assert_eq!(parse_number("1.234", Some(Language::De)), Some(1234.0));
+        assert_eq!(parse_number("12 345,6", Some(Language::Fr)), Some(12345.6));
+        assert_eq!(parse_number("12,345.6", Some(Language::En)), Some(12345.6));
+        assert_eq!(parse_number("-5.5", Some(Language::Nl)), Some(-5.5));
+        assert_eq!(parse_number("22.3", Some(Language::Fr)), None);
+    }
+
+    #[test]
+    fn parse_number_guesses_separators_without_language() {
+        assert_eq!(parse_number("22.3", None), Some(22.3));
+        assert_eq!(parse_number("22,3", None), Some(22.3));
+        assert_eq!(parse_number("12.345,6", None), Some(12345.6));
+        assert_eq!(parse_number("12,345.6", None), Some(12345.6));
+        assert_eq!(parse_number("1.234.567", None), Some(1234567.0));
+        assert_eq!(parse_number("Aan", None), None);
+    }
+
+    #[test]
+    fn parse_duration_returns_seconds_for_hours_and_minutes() {
+        assert_eq!(parse_duration("1234:56"), Some(4445760.0));
+        assert_eq!(parse_duration("0:05"), Some(300.0));
+    }
+
+    #[test]
+    fn parse_duration_returns_seconds_for_hours_minutes_and_seconds() {
+        assert_eq!(parse_duration("00:12:34"), Some(754.0));
+        assert_eq!(parse_duration("12:00:01"), Some(43201.0));
+    }
+
+    #[test]
+    fn parse_duration_returns_none_for_non_durations() {
+        assert_eq!(parse_duration("22.3"), None);
+        assert_eq!(parse_duration("12345"), None);
+        assert_eq!(parse_duration("12:75"), None);
+        assert_eq!(parse_duration("1:2:3:4"), None);
+        assert_eq!(parse_duration("-1:00"), None);
+    }
+
+    #[test]
+    fn deserialize_value_mapping_from_yaml() {
+        let builtin: ValueMapping = serde_yaml::from_str("onOff").unwrap();
+        let custom: ValueMapping = serde_yaml::from_str("Verwarmen: 1\nTapwater: 2").unwrap();
+
+        assert_eq!(builtin, ValueMapping::Builtin(BuiltinValueMapping::OnOff));
+        assert_eq!(custom.map("Tapwater"), Some(2.0));
+    }
+}
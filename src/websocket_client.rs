@@ -1,24 +1,70 @@
-use crate::model::{Config, ConfigSample};
+use crate::content::ContentPage;
+use crate::discovery::{discover_samples, DiscoveredSample, DEFAULT_ENTITY_NAME};
+use crate::error::LuxtronikError;
+use crate::failure::{
+    check_failed_samples, failed_samples_sample, record_failed_samples, FailedSample,
+};
+use crate::language::{name_matches, Language};
+use crate::model::{Config, ConfigController, ConfigSample, MissingValuePolicy};
+use crate::navigation_cache::{NavigationCache, NavigationCacheClient};
+use crate::runtime;
+use crate::sanitizer::sanitize_samples;
+use crate::unit::{SampleUnits, Unit};
+use crate::validation::{suggest, validate_sample, SampleValidation, ValidationResult};
 use chrono::Utc;
 use jarvis_lib::measurement_client::MeasurementClient;
-use jarvis_lib::model::{Measurement, MetricType, Sample};
-use regex::Regex;
+use jarvis_lib::model::{Measurement, Sample};
 use serde::Deserialize;
 use serde_xml_rs::from_str;
 use std::collections::HashMap;
-use std::env;
 use std::error::Error;
-use tracing::{debug, info};
+use std::future::Future;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
 use uuid::Uuid;
 
-use websocket::client::ClientBuilder;
-use websocket::OwnedMessage;
+use futures_util::future::join_all;
+use futures_util::{SinkExt, StreamExt};
+use tokio::net::TcpStream;
+use tokio::time::{sleep, timeout};
+use tokio_tungstenite::tungstenite;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+
+type Connection = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const SESSION_SLOT_WAIT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timeouts {
+    pub connect: Duration,
+    pub response: Duration,
+    // the whole scrape including retries, kept below the cronjob's activeDeadlineSeconds
+    pub total: Duration,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            connect: Duration::from_secs(10),
+            response: Duration::from_secs(10),
+            total: Duration::from_secs(180),
+        }
+    }
+}
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct WebsocketClientConfig {
     host_address: String,
     host_port: u32,
     login_code: String,
+    timeouts: Timeouts,
+    max_retries: u32,
 }
 
 impl WebsocketClientConfig {
@@ -26,32 +72,35 @@ impl WebsocketClientConfig {
         host_address: String,
         host_port: u32,
         login_code: String,
+        timeouts: Timeouts,
+        max_retries: u32,
     ) -> Result<Self, Box<dyn Error>> {
         let config = Self {
             host_address,
             host_port,
             login_code,
+            timeouts,
+            max_retries,
         };
 
         debug!("{:?}", config);
 
         Ok(config)
     }
+}
 
-    pub fn from_env() -> Result<Self, Box<dyn Error>> {
-        let host_address =
-            env::var("WEBSOCKET_HOST_IP").unwrap_or_else(|_| "127.0.0.1".to_string());
-        let host_port: u32 = env::var("WEBSOCKET_HOST_PORT")
-            .unwrap_or_else(|_| "8214".to_string())
-            .parse()?;
-        let login_code = env::var("WEBSOCKET_LOGIN_CODE")?;
-
-        Self::new(host_address, host_port, login_code)
-    }
+// the measurements of one controller, with its navigation cache when it resolved page ids that aren't stored yet
+struct ControllerMeasurements {
+    measurements: Vec<Measurement>,
+    navigation_cache: Option<NavigationCache>,
 }
 
 pub struct WebsocketClient {
     config: WebsocketClientConfig,
+    navigation_cache_client: Option<NavigationCacheClient>,
+    // without sessions every measurement logs in and closes the connection afterwards
+    sessions: Option<Sessions>,
+    sample_units: Option<SampleUnits>,
 }
 
 impl MeasurementClient<Config> for WebsocketClient {
@@ -60,6 +109,201 @@ impl MeasurementClient<Config> for WebsocketClient {
         config: Config,
         last_measurement: Option<Vec<Measurement>>,
     ) -> Result<Vec<Measurement>, Box<dyn Error>> {
+        // the measurement client interface is synchronous
+        let measurements = runtime::block_on(self.read_measurements(config, last_measurement))?;
+
+        Ok(measurements)
+    }
+}
+
+impl WebsocketClient {
+    pub fn new(
+        config: WebsocketClientConfig,
+        navigation_cache_client: Option<NavigationCacheClient>,
+        sessions: Option<Sessions>,
+        sample_units: Option<SampleUnits>,
+    ) -> Self {
+        Self {
+            config,
+            navigation_cache_client,
+            sessions,
+            sample_units,
+        }
+    }
+
+    // a client for one of the configured controllers, defaulting port and login code to this client's
+    pub fn for_controller(&self, controller: &ConfigController) -> WebsocketClient {
+        WebsocketClient::new(
+            WebsocketClientConfig {
+                host_address: controller.host.clone(),
+                host_port: controller.port.unwrap_or(self.config.host_port),
+                login_code: controller
+                    .login_code
+                    .clone()
+                    .unwrap_or_else(|| self.config.login_code.clone()),
+                timeouts: self.config.timeouts,
+                max_retries: self.config.max_retries,
+            },
+            self.navigation_cache_client.clone(),
+            self.sessions.clone(),
+            self.sample_units.clone(),
+        )
+    }
+
+    pub async fn read_measurements(
+        &self,
+        config: Config,
+        last_measurement: Option<Vec<Measurement>>,
+    ) -> Result<Vec<Measurement>, LuxtronikError> {
+        if config.controllers.is_empty() {
+            let controller_measurements = self
+                .read_controller_measurements(&config, last_measurement.as_deref())
+                .await?;
+            self.store_navigation_caches(
+                controller_measurements
+                    .navigation_cache
+                    .into_iter()
+                    .collect(),
+            )
+            .await;
+
+            return Ok(controller_measurements.measurements);
+        }
+
+        // the samples of all controllers are looked up in one last measurement, whether they were combined or not
+        let last_measurement = last_measurement.and_then(|lm| {
+            lm.last().map(|last| Measurement {
+                id: last.id.clone(),
+                source: last.source.clone(),
+                location: last.location.clone(),
+                samples: lm.iter().flat_map(|m| m.samples.clone()).collect(),
+                measured_at_time: last.measured_at_time,
+            })
+        });
+        let last_measurement = last_measurement.as_ref().map(std::slice::from_ref);
+
+        let mut scrapes: Vec<(WebsocketClient, Config)> = Vec::new();
+        if !config.sample_configs.is_empty() {
+            scrapes.push((
+                WebsocketClient::new(
+                    self.config.clone(),
+                    self.navigation_cache_client.clone(),
+                    self.sessions.clone(),
+                    self.sample_units.clone(),
+                ),
+                config.for_sample_configs(config.sample_configs.clone()),
+            ));
+        }
+        for controller in config.controllers.iter() {
+            scrapes.push((
+                self.for_controller(controller),
+                config.for_sample_configs(controller.sample_configs.clone()),
+            ));
+        }
+
+        let results = join_all(scrapes.iter().map(|(client, controller_config)| {
+            client.read_controller_measurements(controller_config, last_measurement)
+        }))
+        .await;
+
+        // a controller that can't be reached only fails the run when all of them fail, otherwise its samples are
+        // reported as failed
+        if results.iter().all(|result| result.is_err()) {
+            return match results.into_iter().next() {
+                Some(result) => result.map(|cm| cm.measurements),
+                None => Ok(vec![]),
+            };
+        }
+
+        let mut measurements = Vec::new();
+        let mut navigation_caches = Vec::new();
+        for ((client, controller_config), result) in scrapes.iter().zip(results) {
+            match result {
+                Ok(controller_measurements) => {
+                    measurements.extend(controller_measurements.measurements);
+                    navigation_caches.extend(controller_measurements.navigation_cache);
+                }
+                Err(e) => {
+                    warn!(
+                        "Reading measurements from controller {}:{} failed: {}",
+                        client.config.host_address, client.config.host_port, e
+                    );
+                    let mut failed_samples = Vec::new();
+                    record_failed_samples(
+                        &mut failed_samples,
+                        &controller_config.sample_configs,
+                        e,
+                    )?;
+                    if !config.publish_failed_samples {
+                        continue;
+                    }
+
+                    measurements.push(Measurement {
+                        id: Uuid::new_v4().to_string(),
+                        source: String::from("jarvis-alpha-innotec-exporter"),
+                        location: config.location.clone(),
+                        samples: vec![failed_samples_sample(
+                            &entity_name(&controller_config.sample_configs),
+                            &failed_samples,
+                        )],
+                        measured_at_time: Utc::now(),
+                    });
+                }
+            }
+        }
+
+        if config.combine_measurements {
+            measurements = measurements
+                .into_iter()
+                .reduce(|mut combined, measurement| {
+                    combined.samples.extend(measurement.samples);
+                    combined
+                })
+                .into_iter()
+                .collect();
+        }
+
+        self.store_navigation_caches(navigation_caches).await;
+
+        Ok(measurements)
+    }
+
+    // the controllers are read concurrently, so their page ids are stored together once all of them are done
+    async fn store_navigation_caches(&self, navigation_caches: Vec<NavigationCache>) {
+        if let Some(navigation_cache_client) = &self.navigation_cache_client {
+            if let Err(e) = navigation_cache_client
+                .store_caches(&navigation_caches)
+                .await
+            {
+                warn!("Storing navigation cache failed: {}", e);
+            }
+        }
+    }
+
+    async fn read_controller_measurements(
+        &self,
+        config: &Config,
+        last_measurement: Option<&[Measurement]>,
+    ) -> Result<ControllerMeasurements, LuxtronikError> {
+        match timeout(
+            self.config.timeouts.total,
+            self.retry(move || self.read_measurement(config, last_measurement)),
+        )
+        .await
+        {
+            Ok(result) => result,
+            Err(_) => Err(LuxtronikError::Timeout(format!(
+                "reading measurements took longer than {:?}",
+                self.config.timeouts.total
+            ))),
+        }
+    }
+
+    async fn read_measurement(
+        &self,
+        config: &Config,
+        last_measurement: Option<&[Measurement]>,
+    ) -> Result<ControllerMeasurements, LuxtronikError> {
         info!("Reading measurements from alpha innotec heatpump...");
 
         let mut measurement = Measurement {
@@ -70,48 +314,380 @@ impl MeasurementClient<Config> for WebsocketClient {
             measured_at_time: Utc::now(),
         };
 
-        let connection = ClientBuilder::new(&format!(
-            "ws://{}:{}",
-            self.config.host_address, self.config.host_port
-        ))?
-        .origin(format!("http://{}", self.config.host_address))
-        .add_protocol("Lux_WS")
-        .connect_insecure()?;
-
-        let (mut receiver, mut sender) = connection.split()?;
-
-        // login
-        let navigation = self.login(&mut receiver, &mut sender)?;
+        let controller = format!("{}:{}", self.config.host_address, self.config.host_port);
+        let session = match self.sessions.as_ref().and_then(|s| s.take(&controller)) {
+            Some(session) => {
+                debug!("Reusing session with controller {}", controller);
+                session
+            }
+            None => {
+                let (connection, login_response) = self.open_session().await?;
+                match self.page_ids(login_response).await {
+                    Ok(page_ids) => Session {
+                        connection,
+                        page_ids,
+                    },
+                    Err(e) => {
+                        close(connection).await;
+                        return Err(e);
+                    }
+                }
+            }
+        };
+        let Session {
+            mut connection,
+            mut page_ids,
+        } = session;
+
+        let language = match config.language {
+            Some(language) => Some(language),
+            None => page_ids.language(),
+        };
+        debug!("Controller language: {:?}", language);
 
         // get measurement samples
+        let sample_count = config.sample_configs.len();
+        let entity_name = entity_name(&config.sample_configs);
         let grouped_sample_configs =
-            self.group_sample_configs_per_navigation(config.sample_configs);
+            self.group_sample_configs_per_navigation(config.sample_configs.clone());
+
+        let last_samples = match last_measurement {
+            Some(lm) if !lm.is_empty() => lm[lm.len() - 1].samples.clone(),
+            _ => vec![],
+        };
+
+        let result = self
+            .get_samples(
+                grouped_sample_configs,
+                &mut connection,
+                &mut page_ids,
+                &last_samples,
+                language,
+            )
+            .await;
+
+        let navigation_cache = if page_ids.changed && result.is_ok() {
+            page_ids.changed = false;
+            Some(page_ids.cache.clone())
+        } else {
+            None
+        };
+
+        match &self.sessions {
+            Some(sessions) if result.is_ok() => sessions.put(
+                &controller,
+                Session {
+                    connection,
+                    page_ids,
+                },
+            ),
+            _ => close(connection).await,
+        }
+        let (samples, failed_samples) = result?;
 
-        measurement.samples = self.get_samples(
-            grouped_sample_configs,
-            &mut receiver,
-            &mut sender,
-            navigation,
-        )?;
+        check_failed_samples(&failed_samples, sample_count, config.max_failed_samples)?;
+        measurement.samples = samples;
 
         if config.sanitize_samples {
             if let Some(lm) = last_measurement {
                 if !lm.is_empty() {
                     measurement.samples =
-                        self.sanitize_samples(measurement.samples, &lm[lm.len() - 1].samples)
+                        sanitize_samples(measurement.samples, &lm[lm.len() - 1].samples)
                 }
             }
         }
 
+        if config.publish_failed_samples {
+            measurement
+                .samples
+                .push(failed_samples_sample(&entity_name, &failed_samples));
+        }
+
         info!("Read measurement from alpha innotec heatpump");
 
-        Ok(vec![measurement])
+        Ok(ControllerMeasurements {
+            measurements: vec![measurement],
+            navigation_cache,
+        })
     }
-}
 
-impl WebsocketClient {
-    pub fn new(config: WebsocketClientConfig) -> Self {
-        Self { config }
+    // retries retryable failures with exponential backoff, reconnecting on every attempt
+    async fn retry<T, F, Fut>(&self, mut operation: F) -> Result<T, LuxtronikError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, LuxtronikError>>,
+    {
+        let mut attempt = 0;
+        let mut backoff = INITIAL_BACKOFF;
+
+        loop {
+            match operation().await {
+                Ok(result) => return Ok(result),
+                Err(e) if attempt < self.config.max_retries && e.is_retryable() => {
+                    attempt += 1;
+                    warn!(
+                        "Attempt {} of {} failed, retrying in {:?}: {}",
+                        attempt,
+                        self.config.max_retries + 1,
+                        backoff,
+                        e
+                    );
+                    sleep(backoff).await;
+                    backoff *= 2;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    // reads every item on every page of the navigation tree, for building a config
+    pub async fn discover(
+        &self,
+        entity_name: &str,
+    ) -> Result<Vec<DiscoveredSample>, LuxtronikError> {
+        info!("Discovering items on alpha innotec heatpump...");
+
+        let (mut connection, navigation) = self.open_navigation_session().await?;
+
+        let language = navigation.detect_language();
+        debug!("Controller language: {:?}", language);
+
+        let result = async {
+            let mut samples = Vec::new();
+            for (nav, navigation_id) in navigation.get_leaf_paths() {
+                info!("Fetching values from page {}...", nav);
+                let response_message = self
+                    .send_and_await(&mut connection, format!("GET;{}", navigation_id))
+                    .await?;
+
+                // pages like the clock programs aren't lists of values, skip rather than abort the discovery
+                match ContentPage::from_response(&response_message) {
+                    Ok(page) => {
+                        samples.extend(discover_samples(&nav, &page, entity_name, language))
+                    }
+                    Err(e) => warn!("Skipping page {}: {}", nav, e),
+                }
+            }
+
+            Ok::<_, LuxtronikError>(samples)
+        }
+        .await;
+        close(connection).await;
+        let samples = result?;
+
+        info!(
+            "Discovered {} items on alpha innotec heatpump",
+            samples.len()
+        );
+
+        Ok(samples)
+    }
+
+    // resolves every configured sample against the live controller without exporting anything
+    pub async fn validate(&self, config: &Config) -> Vec<SampleValidation> {
+        if config.sample_configs.is_empty() {
+            return vec![];
+        }
+
+        info!("Validating config against alpha innotec heatpump...");
+
+        // a controller that can't be reached fails its samples, the report still covers the other controllers
+        let (mut connection, navigation) = match self.open_navigation_session().await {
+            Ok(session) => session,
+            Err(e) => {
+                return failed_validations(
+                    &config.sample_configs,
+                    &format!(
+                        "Connecting to controller {}:{} failed: {}",
+                        self.config.host_address, self.config.host_port, e
+                    ),
+                )
+            }
+        };
+
+        let language = config.language.or_else(|| navigation.detect_language());
+        debug!("Controller language: {:?}", language);
+
+        let leaf_paths = navigation.get_leaf_paths();
+        let paths: Vec<&str> = leaf_paths.iter().map(|(path, _)| path.as_str()).collect();
+
+        // a page that can't be fetched or parsed fails its samples, the report still covers all the others
+        let mut pages: HashMap<String, Result<ContentPage, String>> = HashMap::new();
+        let mut validations = Vec::new();
+
+        for sample_config in config.sample_configs.iter() {
+            let navigation_id =
+                match navigation.get_navigation_item_id(&sample_config.navigation, language) {
+                    Ok(navigation_id) => navigation_id,
+                    Err(e) => {
+                        validations.push(SampleValidation::new(
+                            sample_config,
+                            ValidationResult::Fail(
+                                e.to_string(),
+                                suggest(&sample_config.navigation, &paths),
+                            ),
+                        ));
+                        continue;
+                    }
+                };
+
+            if !pages.contains_key(&navigation_id) {
+                info!("Fetching values from page {}...", sample_config.navigation);
+                let page = self
+                    .get_page_by_id(&mut connection, &navigation_id)
+                    .await
+                    .map_err(|e| {
+                        format!("Fetching page {} failed: {}", sample_config.navigation, e)
+                    });
+                pages.insert(navigation_id.clone(), page);
+            }
+
+            let result = match &pages[&navigation_id] {
+                Ok(page) => validate_sample(sample_config, page, language),
+                Err(e) => ValidationResult::Fail(e.clone(), vec![]),
+            };
+            validations.push(SampleValidation::new(sample_config, result));
+        }
+
+        close(connection).await;
+
+        validations
+    }
+
+    // fetches a single page for troubleshooting, returning it with the detected controller language
+    pub async fn read_page(
+        &self,
+        nav: &str,
+    ) -> Result<(ContentPage, Option<Language>), LuxtronikError> {
+        let (mut connection, navigation) = self.open_navigation_session().await?;
+
+        let language = navigation.detect_language();
+        debug!("Controller language: {:?}", language);
+
+        let result = async {
+            let navigation_id = navigation.get_navigation_item_id(nav, language)?;
+            self.get_page_by_id(&mut connection, &navigation_id).await
+        }
+        .await;
+        close(connection).await;
+
+        Ok((result?, language))
+    }
+
+    // the controller serves one websocket client at a time and closes connections beyond that, wait for a slot
+    // instead of failing while another client, such as the controller's own web interface, is connected
+    async fn open_session(&self) -> Result<(Connection, String), LuxtronikError> {
+        let started = Instant::now();
+
+        loop {
+            let mut connection = self.connect().await?;
+
+            match self.login(&mut connection).await {
+                Ok(login_response) => return Ok((connection, login_response)),
+                Err(LuxtronikError::ConnectionClosed(reason))
+                    if started.elapsed() + SESSION_SLOT_WAIT < self.config.timeouts.total =>
+                {
+                    info!(
+                        "Controller closed the connection during login ({}), waiting {:?} for another client to finish...",
+                        reason.as_deref().unwrap_or("no reason"),
+                        SESSION_SLOT_WAIT
+                    );
+                    sleep(SESSION_SLOT_WAIT).await;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    async fn open_navigation_session(&self) -> Result<(Connection, Navigation), LuxtronikError> {
+        let (connection, login_response) = self.open_session().await?;
+
+        match get_navigation_from_response(&login_response) {
+            Ok(navigation) => Ok((connection, navigation)),
+            Err(e) => {
+                close(connection).await;
+                Err(e)
+            }
+        }
+    }
+
+    // page ids from the navigation cache when it's for this controller and firmware, otherwise they're resolved
+    // from the navigation tree sent at login
+    async fn page_ids(&self, login_response: String) -> Result<PageIds, LuxtronikError> {
+        let controller = format!("{}:{}", self.config.host_address, self.config.host_port);
+        let firmware = from_str::<NavigationRoot>(&login_response)
+            .map_err(|e| {
+                LuxtronikError::LoginFailed(format!(
+                    "controller didn't respond with its navigation: {}",
+                    e
+                ))
+            })?
+            .id;
+
+        let cache = match &self.navigation_cache_client {
+            Some(navigation_cache_client) => {
+                match navigation_cache_client.read_cache(&controller).await {
+                    Ok(Some(cache)) if cache.is_for(&controller, &firmware) => {
+                        debug!("Using {} cached page ids", cache.page_ids.len());
+                        Some(cache)
+                    }
+                    Ok(Some(_)) => {
+                        info!(
+                        "Navigation cache is for another controller or firmware, resolving page ids from the navigation"
+                    );
+                        None
+                    }
+                    Ok(None) => None,
+                    Err(e) => {
+                        warn!("Reading navigation cache failed: {}", e);
+                        None
+                    }
+                }
+            }
+            None => None,
+        };
+
+        Ok(PageIds {
+            changed: false,
+            cache: cache.unwrap_or_else(|| NavigationCache::new(&controller, &firmware)),
+            login_response,
+            navigation: None,
+        })
+    }
+
+    async fn connect(&self) -> Result<Connection, LuxtronikError> {
+        let mut request = format!(
+            "ws://{}:{}",
+            self.config.host_address, self.config.host_port
+        )
+        .into_client_request()?;
+        request
+            .headers_mut()
+            .insert("Sec-WebSocket-Protocol", HeaderValue::from_static("Lux_WS"));
+        request.headers_mut().insert(
+            "Origin",
+            HeaderValue::from_str(&format!("http://{}", self.config.host_address))
+                .map_err(|e| tungstenite::Error::HttpFormat(e.into()))?,
+        );
+
+        let address = format!("{}:{}", self.config.host_address, self.config.host_port);
+        let (connection, _) = match timeout(self.config.timeouts.connect, connect_async(request))
+            .await
+        {
+            Ok(Ok(connection)) => connection,
+            Ok(Err(tungstenite::Error::Io(e))) if e.kind() == io::ErrorKind::ConnectionRefused => {
+                return Err(LuxtronikError::ConnectionRefused(address))
+            }
+            Ok(Err(e)) => return Err(e.into()),
+            Err(_) => {
+                return Err(LuxtronikError::Timeout(format!(
+                    "connecting to {} took longer than {:?}",
+                    address, self.config.timeouts.connect
+                )))
+            }
+        };
+
+        Ok(connection)
     }
 
     fn group_sample_configs_per_navigation(
@@ -142,71 +718,75 @@ impl WebsocketClient {
         grouped_sample_configs
     }
 
-    fn send_and_await(
+    async fn send_and_await(
         &self,
-        receiver: &mut websocket::receiver::Reader<std::net::TcpStream>,
-        sender: &mut websocket::sender::Writer<std::net::TcpStream>,
-        message: websocket::OwnedMessage,
-    ) -> Result<String, Box<dyn Error>> {
-        sender.send_message(&message)?;
-
-        for message in receiver.incoming_messages() {
-            match message? {
-                OwnedMessage::Text(text) => {
+        connection: &mut Connection,
+        message: String,
+    ) -> Result<String, LuxtronikError> {
+        connection.send(Message::Text(message)).await?;
+
+        // pings are answered by the websocket stack while reading
+        loop {
+            let message = match timeout(self.config.timeouts.response, connection.next()).await {
+                Ok(Some(Err(tungstenite::Error::ConnectionClosed)))
+                | Ok(Some(Err(tungstenite::Error::AlreadyClosed)))
+                | Ok(None) => return Err(LuxtronikError::ConnectionClosed(None)),
+                Ok(Some(message)) => message?,
+                Err(_) => {
+                    return Err(LuxtronikError::Timeout(format!(
+                        "no response within {:?}",
+                        self.config.timeouts.response
+                    )))
+                }
+            };
+
+            match message {
+                Message::Text(text) => {
                     return Ok(text);
                 }
-                OwnedMessage::Close(_) => {
-                    // return a close
-                    sender.send_message(&OwnedMessage::Close(None))?;
+                Message::Close(frame) => {
+                    // the websocket stack answers the close, surface it instead of waiting for a response
+                    return Err(LuxtronikError::ConnectionClosed(
+                        frame
+                            .map(|f| f.reason.to_string())
+                            .filter(|reason| !reason.is_empty()),
+                    ));
                 }
-                OwnedMessage::Ping(data) => {
-                    // return a pong
-                    sender.send_message(&OwnedMessage::Pong(data))?;
+                Message::Binary(_) => {
+                    return Err(LuxtronikError::Protocol(
+                        "Unexpected binary message, expected a text response".to_string(),
+                    ));
                 }
-                OwnedMessage::Pong(_) => {}
-                OwnedMessage::Binary(_) => {}
+                Message::Ping(_) | Message::Pong(_) | Message::Frame(_) => {}
             }
         }
-
-        Err(Box::<dyn Error>::from(
-            "No response received for login message",
-        ))
     }
 
-    fn login(
-        &self,
-        receiver: &mut websocket::receiver::Reader<std::net::TcpStream>,
-        sender: &mut websocket::sender::Writer<std::net::TcpStream>,
-    ) -> Result<Navigation, Box<dyn Error>> {
-        let response_message = self.send_and_await(
-            receiver,
-            sender,
-            websocket::OwnedMessage::Text(format!("LOGIN;{}", self.config.login_code)),
-        )?;
-
-        let navigation = self.get_navigation_from_response(response_message)?;
-
-        Ok(navigation)
+    async fn login(&self, connection: &mut Connection) -> Result<String, LuxtronikError> {
+        self.send_and_await(connection, format!("LOGIN;{}", self.config.login_code))
+            .await
     }
 
-    fn get_samples(
+    async fn get_samples(
         &self,
         grouped_sample_configs: HashMap<String, Vec<ConfigSample>>,
-        receiver: &mut websocket::receiver::Reader<std::net::TcpStream>,
-        sender: &mut websocket::sender::Writer<std::net::TcpStream>,
-
-        navigation: Navigation,
-    ) -> Result<Vec<Sample>, Box<dyn Error>> {
+        connection: &mut Connection,
+        page_ids: &mut PageIds,
+        last_samples: &[Sample],
+        language: Option<Language>,
+    ) -> Result<(Vec<Sample>, Vec<FailedSample>), LuxtronikError> {
         let mut samples = Vec::new();
+        let mut failed_samples = Vec::new();
 
         for (nav, sample_configs) in grouped_sample_configs {
             info!("Fetching values from page {}...", nav);
-            let navigation_id = navigation.get_navigation_item_id(&nav)?;
-            let response_message = self.send_and_await(
-                receiver,
-                sender,
-                websocket::OwnedMessage::Text(format!("GET;{}", navigation_id)),
-            )?;
+            let page = match self.get_page(connection, page_ids, &nav, language).await {
+                Ok(page) => page,
+                Err(e) => {
+                    record_failed_samples(&mut failed_samples, &sample_configs, e)?;
+                    continue;
+                }
+            };
 
             info!(
                 "Reading {} values from response for page {}...",
@@ -214,108 +794,288 @@ impl WebsocketClient {
                 nav
             );
             for sample_config in sample_configs.iter() {
-                let value = self.get_item_from_response(&sample_config.item, &response_message)?;
-
-                samples.push(Sample {
-                    entity_type: sample_config.entity_type,
-                    entity_name: sample_config.entity_name.clone(),
-                    sample_type: sample_config.sample_type,
-                    sample_name: sample_config.sample_name.clone(),
-                    metric_type: sample_config.metric_type,
-                    value: value * sample_config.value_multiplier,
-                });
+                // with sessions the daemon already checked the config when it started
+                if self.sessions.is_none() && page.is_ambiguous(sample_config, language) {
+                    warn!(
+                        "Item {} occurs more than once on page {}, set itemId, itemOccurrence or itemUnit to select the right one",
+                        sample_config.item, nav
+                    );
+                }
+
+                match self.get_sample(&page, sample_config, last_samples, language) {
+                    Ok(Some(sample)) => samples.push(sample),
+                    Ok(None) => {}
+                    Err(e) => record_failed_samples(
+                        &mut failed_samples,
+                        std::slice::from_ref(sample_config),
+                        e,
+                    )?,
+                }
             }
         }
 
-        Ok(samples)
+        Ok((samples, failed_samples))
     }
 
-    fn get_navigation_from_response(
+    // gets the page for a navigation path; a cached id that leads to another page means the navigation changed, so
+    // the cache is dropped and the id resolved again
+    async fn get_page(
         &self,
-        response_message: String,
-    ) -> Result<Navigation, Box<dyn Error>> {
-        let navigation: Navigation = from_str(&response_message)?;
+        connection: &mut Connection,
+        page_ids: &mut PageIds,
+        nav: &str,
+        language: Option<Language>,
+    ) -> Result<ContentPage, LuxtronikError> {
+        let cached = page_ids.is_cached(nav);
+        let page = self
+            .get_page_by_id(connection, &page_ids.get(nav, language)?)
+            .await?;
+
+        let page_name = nav.rsplit(" > ").next().unwrap_or(nav);
+        if cached && !page.name.is_empty() && !name_matches(page_name, &page.name, language) {
+            warn!(
+                "Cached page id for {} returned page {}, invalidating navigation cache",
+                nav, page.name
+            );
+            page_ids.invalidate();
 
-        Ok(navigation)
+            return self
+                .get_page_by_id(connection, &page_ids.get(nav, language)?)
+                .await;
+        }
+
+        Ok(page)
     }
 
-    fn get_item_from_response(
+    async fn get_page_by_id(
         &self,
-        item: &str,
-        response_message: &str,
-    ) -> Result<f64, Box<dyn Error>> {
-        // <Content><item id='0x4816ac'><name>Aanvoer</name><value>22.0°C</value></item><item id='0x44fdcc'><name>Retour</name><value>22.0°C</value></item><item id='0x4807dc'><name>Retour berekend</name><value>23.0°C</value></item><item id='0x45e1bc'><name>Heetgas</name><value>38.0°C</value></item><item id='0x448894'><name>Buitentemperatuur</name><value>11.6°C</value></item><item id='0x48047c'><name>Gemiddelde temp.</name><value>13.1°C</value></item><item id='0x457724'><name>Tapwater gemeten</name><value>54.2°C</value></item><item id='0x45e97c'><name>Tapwater ingesteld</name><value>57.0°C</value></item><item id='0x45a41c'><name>Bron-in</name><value>10.5°C</value></item><item id='0x480204'><name>Bron-uit</name><value>10.3°C</value></item><item id='0x4803cc'><name>Menggroep2-aanvoer</name><value>22.0°C</value></item><item id='0x4609cc'><name>Menggr2-aanv.ingest.</name><value>19.0°C</value></item><item id='0x45a514'><name>Zonnecollector</name><value>5.0°C</value></item><item id='0x461ecc'><name>Zonneboiler</name><value>150.0°C</value></item><item id='0x4817a4'><name>Externe energiebron</name><value>5.0°C</value></item><item id='0x4646b4'><name>Aanvoer max.</name><value>66.0°C</value></item><item id='0x45e76c'><name>Zuiggasleiding comp.</name><value>19.4°C</value></item><item id='0x4607d4'><name>Comp. verwarming</name><value>37.7°C</value></item><item id='0x43e60c'><name>Oververhitting</name><value>4.8 K</value></item><name>Temperaturen</name></Content>
-
-        let re = Regex::new(&format!(
-            r"<item id='[^']*'><name>{}</name><value>(-?[0-9.]+|---)[^<]*</value></item>",
-            item
-        ))?;
-        let matches = match re.captures(response_message) {
-            Some(m) => m,
-            None => {
-                return Err(Box::<dyn Error>::from(format!(
-                    "No match for item {}",
-                    item
-                )));
-            }
-        };
-
-        if matches.len() != 2 {
-            return Err(Box::<dyn Error>::from(format!(
-                "No match for item {}",
-                item
-            )));
-        }
-
-        return match matches.get(1) {
-            None => Ok(0.0),
-            Some(m) => {
-                let value = m.as_str();
-                if value == "---" {
-                    return Ok(0.0);
-                }
-                Ok(value.parse()?)
-            }
-        };
+        connection: &mut Connection,
+        navigation_id: &str,
+    ) -> Result<ContentPage, LuxtronikError> {
+        let response_message = self
+            .send_and_await(connection, format!("GET;{}", navigation_id))
+            .await?;
+
+        ContentPage::from_response(&response_message)
+            .map_err(|e| LuxtronikError::Parse(e.to_string()))
     }
 
-    fn sanitize_samples(
+    fn get_sample(
         &self,
-        current_samples: Vec<Sample>,
+        page: &ContentPage,
+        sample_config: &ConfigSample,
         last_samples: &[Sample],
-    ) -> Vec<Sample> {
-        let mut sanitized_samples: Vec<Sample> = Vec::new();
-
-        for current_sample in current_samples.into_iter() {
-            // check if there's a corresponding sample in lastSamples and see if the difference with it's value isn't too large
-            let mut sanitize = false;
-            for last_sample in last_samples.iter() {
-                if current_sample.entity_type == last_sample.entity_type
-                    && current_sample.entity_name == last_sample.entity_name
-                    && current_sample.sample_type == last_sample.sample_type
-                    && current_sample.sample_name == last_sample.sample_name
-                    && current_sample.metric_type == last_sample.metric_type
-                {
-                    if current_sample.metric_type == MetricType::Counter
-                        && last_sample.value > 0.0
-                        && current_sample.value / last_sample.value > 1.1
-                    {
-                        sanitize = true;
-                        info!("Value for {} is more than 10 percent larger than the last sampled value {}, keeping previous value instead", current_sample.sample_name, last_sample.value);
-                        sanitized_samples.push(last_sample.clone());
+        language: Option<Language>,
+    ) -> Result<Option<Sample>, LuxtronikError> {
+        let item = page
+            .select_item(sample_config, language)
+            .map_err(|e| LuxtronikError::ItemNotFound(e.to_string()))?;
+
+        if item.is_placeholder() {
+            match sample_config.missing_value {
+                MissingValuePolicy::Zero => {}
+                MissingValuePolicy::Skip => {
+                    info!("Item {} is not available, skipping sample", item.name);
+                    return Ok(None);
+                }
+                MissingValuePolicy::LastKnown => {
+                    let last_sample = last_samples.iter().find(|ls| {
+                        ls.entity_type == sample_config.entity_type
+                            && ls.entity_name == sample_config.entity_name
+                            && ls.sample_type == sample_config.sample_type
+                            && ls.sample_name == sample_config.sample_name
+                            && ls.metric_type == sample_config.metric_type
+                    });
+
+                    if last_sample.is_none() {
+                        warn!(
+                            "Item {} is not available and there's no last known value, skipping sample",
+                            item.name
+                        );
                     }
 
-                    break;
+                    return Ok(last_sample.cloned());
+                }
+                MissingValuePolicy::Fail => {
+                    return Err(LuxtronikError::InvalidValue(format!(
+                        "Item {} is not available",
+                        item.name
+                    )));
                 }
             }
+        }
+
+        let value = item
+            .value(sample_config.value_mapping.as_ref(), language)
+            .and_then(|value| {
+                // a legacy multiplier converts to si as well
+                if item
+                    .unit
+                    .is_legacy_multiplier(sample_config.sample_type, sample_config.value_multiplier)
+                {
+                    warn!(
+                        "Sample {} has a valueMultiplier of {} that converts {} to si itself, skipping the automatic conversion; remove the valueMultiplier",
+                        sample_config.sample_name, sample_config.value_multiplier, item.unit
+                    );
+                    return Ok(value);
+                }
+                item.unit.to_si(value, sample_config.sample_type)
+            })
+            .map_err(|e| LuxtronikError::InvalidValue(e.to_string()))?;
+
+        if let Some(sample_units) = &self.sample_units {
+            sample_units.set(
+                &sample_config.entity_name,
+                &sample_config.sample_name,
+                Some(item.unit.clone()).filter(|unit| *unit != Unit::None),
+            );
+        }
+
+        Ok(Some(Sample {
+            entity_type: sample_config.entity_type,
+            entity_name: sample_config.entity_name.clone(),
+            sample_type: sample_config.sample_type,
+            sample_name: sample_config.sample_name.clone(),
+            metric_type: sample_config.metric_type,
+            value: value * sample_config.value_multiplier,
+        }))
+    }
+}
+
+// ends the session so the controller frees its client slot right away
+async fn close(mut connection: Connection) {
+    if let Err(e) = connection.close(None).await {
+        debug!("Closing connection failed: {}", e);
+    }
+}
+
+// a logged in connection with the page ids resolved so far
+struct Session {
+    connection: Connection,
+    page_ids: PageIds,
+}
+
+// sessions kept open between measurements in daemon mode, by controller address; a session is taken out while
+// it's in use and only put back after a successful measurement, so a failing one gets replaced by a new login
+#[derive(Clone, Default)]
+pub struct Sessions {
+    sessions: Arc<Mutex<HashMap<String, Session>>>,
+}
+
+impl Sessions {
+    fn take(&self, controller: &str) -> Option<Session> {
+        self.sessions.lock().unwrap().remove(controller)
+    }
+
+    fn put(&self, controller: &str, session: Session) {
+        self.sessions
+            .lock()
+            .unwrap()
+            .insert(controller.to_string(), session);
+    }
+
+    pub async fn close_all(&self) {
+        let sessions: Vec<Session> = self
+            .sessions
+            .lock()
+            .unwrap()
+            .drain()
+            .map(|(_, session)| session)
+            .collect();
+
+        for session in sessions.into_iter() {
+            close(session.connection).await;
+        }
+    }
+}
+
+fn failed_validations(sample_configs: &[ConfigSample], reason: &str) -> Vec<SampleValidation> {
+    sample_configs
+        .iter()
+        .map(|sc| SampleValidation::new(sc, ValidationResult::Fail(reason.to_string(), vec![])))
+        .collect()
+}
+
+// entity name for the samples about the exporter itself, such as the failed samples count
+fn entity_name(sample_configs: &[ConfigSample]) -> String {
+    sample_configs
+        .first()
+        .map(|sc| sc.entity_name.clone())
+        .unwrap_or_else(|| DEFAULT_ENTITY_NAME.to_string())
+}
+
+fn get_navigation_from_response(response_message: &str) -> Result<Navigation, LuxtronikError> {
+    from_str(response_message).map_err(|e| {
+        LuxtronikError::LoginFailed(format!(
+            "controller didn't respond with its navigation: {}",
+            e
+        ))
+    })
+}
+
+// the page ids used during a measurement, backed by the navigation cache; the navigation tree is only parsed when
+// an id isn't cached
+struct PageIds {
+    cache: NavigationCache,
+    login_response: String,
+    navigation: Option<Navigation>,
+    // whether the cache needs storing after the measurement
+    changed: bool,
+}
 
-            if !sanitize {
-                sanitized_samples.push(current_sample);
+impl PageIds {
+    fn navigation(&mut self) -> Result<&Navigation, LuxtronikError> {
+        if self.navigation.is_none() {
+            let navigation = get_navigation_from_response(&self.login_response)?;
+            if self.cache.language.is_none() {
+                self.cache.language = navigation.detect_language();
             }
+            self.navigation = Some(navigation);
+        }
+
+        Ok(self.navigation.as_ref().unwrap())
+    }
+
+    fn language(&mut self) -> Option<Language> {
+        if self.cache.language.is_none() {
+            // without a navigation the language simply stays unknown, the failure surfaces when resolving a page
+            let _ = self.navigation();
+        }
+
+        self.cache.language
+    }
+
+    fn is_cached(&self, nav: &str) -> bool {
+        self.cache.page_ids.contains_key(nav)
+    }
+
+    fn get(&mut self, nav: &str, language: Option<Language>) -> Result<String, LuxtronikError> {
+        if let Some(navigation_id) = self.cache.page_ids.get(nav) {
+            return Ok(navigation_id.clone());
         }
 
-        sanitized_samples
+        let navigation_id = self.navigation()?.get_navigation_item_id(nav, language)?;
+        self.cache
+            .page_ids
+            .insert(nav.to_string(), navigation_id.clone());
+        self.changed = true;
+
+        Ok(navigation_id)
     }
+
+    fn invalidate(&mut self) {
+        self.cache.page_ids.clear();
+        self.cache.language = self
+            .navigation
+            .as_ref()
+            .and_then(|navigation| navigation.detect_language());
+        self.changed = true;
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct NavigationRoot {
+    id: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -334,7 +1094,40 @@ struct NavigationItem {
 }
 
 impl Navigation {
-    fn get_navigation_item_id(&self, item_path: &str) -> Result<String, Box<dyn Error>> {
+    fn detect_language(&self) -> Option<Language> {
+        self.items
+            .first()
+            .and_then(|item| Language::from_menu_name(&item.name))
+    }
+
+    // paths and ids of all pages without sub pages, as navigation strings in the controller language
+    fn get_leaf_paths(&self) -> Vec<(String, String)> {
+        fn walk(items: &[NavigationItem], prefix: &str, paths: &mut Vec<(String, String)>) {
+            for item in items.iter() {
+                let path = if prefix.is_empty() {
+                    item.name.clone()
+                } else {
+                    format!("{} > {}", prefix, item.name)
+                };
+
+                if item.items.is_empty() {
+                    paths.push((path, item.id.clone()));
+                } else {
+                    walk(&item.items, &path, paths);
+                }
+            }
+        }
+
+        let mut paths = Vec::new();
+        walk(&self.items, "", &mut paths);
+        paths
+    }
+
+    fn get_navigation_item_id(
+        &self,
+        item_path: &str,
+        language: Option<Language>,
+    ) -> Result<String, LuxtronikError> {
         let item_path_parts: Vec<&str> = item_path.split(" > ").collect();
 
         let mut navigation_id: String = "".to_string();
@@ -343,7 +1136,7 @@ impl Navigation {
         for part in item_path_parts.iter() {
             let mut exists = false;
             for item in items.iter() {
-                if *part == item.name {
+                if name_matches(part, &item.name, language) {
                     exists = true;
 
                     navigation_id = item.id.clone();
@@ -354,10 +1147,12 @@ impl Navigation {
             }
 
             if !exists {
-                return Err(Box::<dyn Error>::from(format!(
-                    "Item {} does not exist",
-                    part
-                )));
+                return Err(LuxtronikError::PageNotFound(
+                    match language.and_then(|l| l.translate(part)) {
+                        Some(translation) => format!("{} ({})", part, translation),
+                        None => part.to_string(),
+                    },
+                ));
             }
         }
 
@@ -370,6 +1165,22 @@ mod tests {
     use super::*;
     use jarvis_lib::model::{EntityType, MetricType, SampleType};
 
+    fn websocket_client() -> WebsocketClient {
+        WebsocketClient::new(
+            WebsocketClientConfig::new(
+                "192.168.178.94".to_string(),
+                8214,
+                "999999".to_string(),
+                Timeouts::default(),
+                3,
+            )
+            .unwrap(),
+            None,
+            None,
+            None,
+        )
+    }
+
     #[test]
     fn deserialize_navigation_xml() {
         let xml_string = "<Navigation id=\"0x45cd88\"><item id=\"0x45df90\"><name>Informatie</name><item id=\"0x45df90\"><name>Temperaturen</name></item><item id=\"0x455968\"><name>Ingangen</name></item></item><item id=\"0x450798\"><name>Instelling</name></item><item id=\"0x3dc420\"><name>Klokprogramma</name></item><item id=\"0x45c7b0\"><name>Toegang: Gebruiker</name></item></Navigation>";
@@ -389,6 +1200,31 @@ mod tests {
         assert_eq!(navigation.items[0].items[1].id, "0x455968".to_string());
     }
 
+    #[test]
+    fn get_leaf_paths_returns_pages_without_sub_pages() {
+        let xml_string = "<Navigation id=\"0x45cd88\"><item id=\"0x45e068\"><name>Informatie</name><item id=\"0x45df90\"><name>Temperaturen</name></item><item id=\"0x455968\"><name>Ingangen</name></item></item><item id=\"0x3dc420\"><name>Klokprogramma</name><item id=\"0x453560\"><name>Verwarmen</name><item id=\"0x45e118\"><name>Week</name></item></item></item><item id=\"0x45c7b0\"><name>Toegang: Gebruiker</name></item></Navigation>";
+        let navigation: Navigation = from_str(xml_string).unwrap();
+
+        // act
+        let paths = navigation.get_leaf_paths();
+
+        assert_eq!(
+            paths,
+            vec![
+                (
+                    "Informatie > Temperaturen".to_string(),
+                    "0x45df90".to_string()
+                ),
+                ("Informatie > Ingangen".to_string(), "0x455968".to_string()),
+                (
+                    "Klokprogramma > Verwarmen > Week".to_string(),
+                    "0x45e118".to_string()
+                ),
+                ("Toegang: Gebruiker".to_string(), "0x45c7b0".to_string()),
+            ]
+        );
+    }
+
     #[test]
     fn get_navigation_item_id_returns_id_if_it_exists() {
         // <Navigation id='0x45cd88'><item id='0x45e068'><name>Informatie</name><item id='0x45df90'><name>Temperaturen</name></item><item id='0x455968'><name>Ingangen</name></item><item id='0x455760'><name>Uitgangen</name></item><item id='0x45bf10'><name>Aflooptijden</name></item><item id='0x456f08'><name>Bedrijfsuren</name></item><item id='0x4643a8'><name>Storingsbuffer</name></item><item id='0x3ddfa8'><name>Afschakelingen</name></item><item id='0x45d840'><name>Installatiestatus</name></item><item id='0x460cb8'><name>Energie</name></item><item id='0x4586a8'><name>GBS</name></item></item><item id='0x450798'><name>Instelling</name><item id='0x460bd0'><name>Bedrijfsmode</name></item><item id='0x461170'><name>Temperaturen</name></item><item id='0x462988'><name>Systeeminstelling</name></item></item><item id='0x3dc420'><name>Klokprogramma</name><readOnly>true</readOnly><item id='0x453560'><name>Verwarmen</name><readOnly>true</readOnly><item id='0x45e118'><name>Week</name></item><item id='0x45df00'><name>5+2</name></item><item id='0x45c200'><name>Dagen (Ma, Di,...)</name></item></item><item id='0x43e8e8'><name>Warmwater</name><readOnly>true</readOnly><item id='0x4642a8'><name>Week</name></item><item id='0x463940'><name>5+2</name></item><item id='0x463b68'><name>Dagen (Ma, Di,...)</name></item></item><item id='0x3dcc00'><name>Zwembad</name><readOnly>true</readOnly><item id='0x455580'><name>Week</name></item><item id='0x463f78'><name>5+2</name></item><item id='0x462690'><name>Dagen (Ma, Di,...)</name></item></item></item><item id='0x45c7b0'><name>Toegang: Gebruiker</name></item></Navigation>
@@ -431,7 +1267,7 @@ mod tests {
         };
 
         let item_id = navigation
-            .get_navigation_item_id(&"Informatie".to_string())
+            .get_navigation_item_id(&"Informatie".to_string(), None)
             .unwrap();
 
         assert_eq!(item_id, "0x45df90".to_string());
@@ -479,101 +1315,231 @@ mod tests {
         };
 
         let item_id = navigation
-            .get_navigation_item_id(&"Informatie > Ingangen".to_string())
+            .get_navigation_item_id(&"Informatie > Ingangen".to_string(), None)
             .unwrap();
 
         assert_eq!(item_id, "0x455968".to_string());
     }
 
     #[test]
-    fn get_item_from_response_returns_value_for_item_without_unit() {
-        let websocket_client = WebsocketClient::new(
-            WebsocketClientConfig::new("192.168.178.94".to_string(), 8214, "999999".to_string())
-                .unwrap(),
-        );
-
-        let response_message = "<Content><item id='0x4816ac'><name>Aanvoer</name><value>22.3°C</value></item><item id='0x44fdcc'><name>Retour</name><value>22.0°C</value></item><item id='0x4807dc'><name>Retour berekend</name><value>23.0°C</value></item><item id='0x45e1bc'><name>Heetgas</name><value>38.0°C</value></item><item id='0x448894'><name>Buitentemperatuur</name><value>11.6°C</value></item><item id='0x48047c'><name>Gemiddelde temp.</name><value>13.1°C</value></item><item id='0x457724'><name>Tapwater gemeten</name><value>54.2°C</value></item><item id='0x45e97c'><name>Tapwater ingesteld</name><value>57.0°C</value></item><item id='0x45a41c'><name>Bron-in</name><value>10.5°C</value></item><item id='0x480204'><name>Bron-uit</name><value>10.3°C</value></item><item id='0x4803cc'><name>Menggroep2-aanvoer</name><value>22.0°C</value></item><item id='0x4609cc'><name>Menggr2-aanv.ingest.</name><value>19.0°C</value></item><item id='0x45a514'><name>Zonnecollector</name><value>5.0°C</value></item><item id='0x461ecc'><name>Zonneboiler</name><value>150.0°C</value></item><item id='0x4817a4'><name>Externe energiebron</name><value>5.0°C</value></item><item id='0x4646b4'><name>Aanvoer max.</name><value>66.0°C</value></item><item id='0x45e76c'><name>Zuiggasleiding comp.</name><value>19.4°C</value></item><item id='0x4607d4'><name>Comp. verwarming</name><value>37.7°C</value></item><item id='0x43e60c'><name>Oververhitting</name><value>4.8 K</value></item><name>Temperaturen</name></Content>".to_string();
+    fn get_navigation_item_id_returns_id_for_canonical_english_path() {
+        let navigation = Navigation {
+            items: vec![NavigationItem {
+                id: "0x45e068".to_string(),
+                name: "Informationen".to_string(),
+                items: vec![
+                    NavigationItem {
+                        id: "0x45df90".to_string(),
+                        name: "Temperaturen".to_string(),
+                        items: vec![],
+                    },
+                    NavigationItem {
+                        id: "0x455968".to_string(),
+                        name: "Eingänge".to_string(),
+                        items: vec![],
+                    },
+                ],
+            }],
+        };
 
-        //act
-        let value = websocket_client
-            .get_item_from_response(&"Aanvoer".to_string(), &response_message)
+        let item_id = navigation
+            .get_navigation_item_id("Information > Inputs", navigation.detect_language())
             .unwrap();
 
-        assert_eq!(value, 22.3);
+        assert_eq!(item_id, "0x455968".to_string());
     }
 
     #[test]
-    #[should_panic]
-    fn get_item_from_response_returns_error_if_item_id_is_not_in_response() {
-        let websocket_client = WebsocketClient::new(
-            WebsocketClientConfig::new("192.168.178.94".to_string(), 8214, "999999".to_string())
-                .unwrap(),
-        );
+    fn page_ids_resolves_uncached_ids_from_navigation_and_resets_on_invalidate() {
+        let login_response = "<Navigation id='0x45cd88'><item id='0x45e068'><name>Informatie</name><item id='0x45df90'><name>Temperaturen</name></item><item id='0x455968'><name>Ingangen</name></item></item></Navigation>".to_string();
+        let mut cache = NavigationCache::new("192.168.195.4:8214", "0x45cd88");
+        cache
+            .page_ids
+            .insert("Informatie > Ingangen".to_string(), "0x400000".to_string());
+        let mut page_ids = PageIds {
+            cache,
+            login_response,
+            navigation: None,
+            changed: false,
+        };
 
-        let response_message = "<Content><item id='0x4816ac'><name>Aanvoer</name><value>22.3°C</value></item><item id='0x44fdcc'><name>Retour</name><value>22.0°C</value></item><item id='0x4807dc'><name>Retour berekend</name><value>23.0°C</value></item><item id='0x45e1bc'><name>Heetgas</name><value>38.0°C</value></item><item id='0x448894'><name>Buitentemperatuur</name><value>11.6°C</value></item><item id='0x48047c'><name>Gemiddelde temp.</name><value>13.1°C</value></item><item id='0x457724'><name>Tapwater gemeten</name><value>54.2°C</value></item><item id='0x45e97c'><name>Tapwater ingesteld</name><value>57.0°C</value></item><item id='0x45a41c'><name>Bron-in</name><value>10.5°C</value></item><item id='0x480204'><name>Bron-uit</name><value>10.3°C</value></item><item id='0x4803cc'><name>Menggroep2-aanvoer</name><value>22.0°C</value></item><item id='0x4609cc'><name>Menggr2-aanv.ingest.</name><value>19.0°C</value></item><item id='0x45a514'><name>Zonnecollector</name><value>5.0°C</value></item><item id='0x461ecc'><name>Zonneboiler</name><value>150.0°C</value></item><item id='0x4817a4'><name>Externe energiebron</name><value>5.0°C</value></item><item id='0x4646b4'><name>Aanvoer max.</name><value>66.0°C</value></item><item id='0x45e76c'><name>Zuiggasleiding comp.</name><value>19.4°C</value></item><item id='0x4607d4'><name>Comp. verwarming</name><value>37.7°C</value></item><item id='0x43e60c'><name>Oververhitting</name><value>4.8 K</value></item><name>Temperaturen</name></Content>".to_string();
+        // act
+        let cached = page_ids.get("Informatie > Ingangen", None).unwrap();
+        let navigation_parsed = page_ids.navigation.is_some();
+        let resolved = page_ids.get("Informatie > Temperaturen", None).unwrap();
+        page_ids.invalidate();
+        let invalidated = page_ids.get("Informatie > Ingangen", None).unwrap();
+
+        assert_eq!(cached, "0x400000".to_string());
+        assert!(!navigation_parsed);
+        assert_eq!(resolved, "0x45df90".to_string());
+        assert_eq!(invalidated, "0x455968".to_string());
+        assert_eq!(page_ids.language(), Some(Language::Nl));
+        assert!(page_ids.changed);
+    }
 
-        //act
-        let _ = websocket_client
-            .get_item_from_response(&"DoesNotExist".to_string(), &response_message)
-            .unwrap();
+    #[test]
+    fn get_navigation_item_id_returns_page_not_found_with_translation() {
+        let navigation = Navigation {
+            items: vec![NavigationItem {
+                id: "0x45e068".to_string(),
+                name: "Informationen".to_string(),
+                items: vec![],
+            }],
+        };
+
+        // act
+        let result = navigation.get_navigation_item_id("Information > Inputs", Some(Language::De));
+
+        match result {
+            Err(LuxtronikError::PageNotFound(page)) => {
+                assert_eq!(page, "Inputs (Eingänge)".to_string())
+            }
+            other => panic!("Expected page not found, got {:?}", other),
+        }
     }
 
     #[test]
-    fn get_item_from_response_returns_value_for_item_with_pressure_unit() {
-        let websocket_client = WebsocketClient::new(
-            WebsocketClientConfig::new("192.168.178.94".to_string(), 8214, "999999".to_string())
-                .unwrap(),
+    fn get_sample_returns_item_not_found_for_unknown_item() {
+        let websocket_client = websocket_client();
+        let page = ContentPage::from_response("<Content><item id='0x461ecc'><name>Zonneboiler</name><value>150.0°C</value></item><name>Temperaturen</name></Content>").unwrap();
+
+        // act
+        let result = websocket_client.get_sample(
+            &page,
+            &sample_config_with_missing_value(MissingValuePolicy::Zero),
+            &[],
+            None,
         );
 
-        let response_message = "<Content><item id='0x4e7944'><name>ASD</name><value>Aan</value></item><item id='0x4ffbfc'><name>EVU</name><value>Aan</value></item><item id='0x4ef3b4'><name>HD</name><value>Uit</value></item><item id='0x4dac64'><name>MOT</name><value>Aan</value></item><item id='0x4ca4c4'><name>SWT</name><value>Uit</value></item><item id='0x4fa864'><name>Analoog-In 21</name><value>0.00 V</value></item><item id='0x4d5f1c'><name>Analoog-In 22</name><value>0.00 V</value></item><item id='0x4e6a3c'><name>HD</name><value>8.10 bar</value></item><item id='0x4ca47c'><name>ND</name><value>8.38 bar</value></item><item id='0x4e8004'><name>Debiet</name><value>1200 l/h</value></item><name>Ingangen</name></Content>".to_string();
+        assert!(matches!(result, Err(LuxtronikError::ItemNotFound(_))));
+    }
+
+    #[test]
+    fn get_sample_skips_si_conversion_for_legacy_value_multiplier() {
+        let websocket_client = websocket_client();
+        let page = ContentPage::from_response("<Content><item id='0x4ef0c4'><name>Warmwater</name><value>12.5 kWh</value></item><name>Energie</name></Content>").unwrap();
+        let mut legacy_sample_config = sample_config_with_missing_value(MissingValuePolicy::Zero);
+        legacy_sample_config.sample_type = SampleType::Energy;
+        legacy_sample_config.item = "Warmwater".to_string();
+        legacy_sample_config.value_multiplier = 3600000.0;
+        let mut sample_config = legacy_sample_config.clone();
+        sample_config.value_multiplier = 1.0;
+
+        // act
+        let legacy_sample = websocket_client
+            .get_sample(&page, &legacy_sample_config, &[], None)
+            .unwrap()
+            .unwrap();
+        let sample = websocket_client
+            .get_sample(&page, &sample_config, &[], None)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(legacy_sample.value, 45000000.0);
+        assert_eq!(sample.value, 45000000.0);
+    }
+
+    fn sample_config_with_missing_value(missing_value: MissingValuePolicy) -> ConfigSample {
+        ConfigSample {
+            missing_value,
+            ..ConfigSample::for_test(
+                SampleType::Temperature,
+                "Zonnecollector",
+                "Informatie > Temperaturen",
+                "Zonnecollector",
+            )
+        }
+    }
+
+    #[test]
+    fn get_sample_applies_missing_value_policy_to_placeholder() {
+        let websocket_client = websocket_client();
+        let page = ContentPage::from_response("<Content><item id='0x45a514'><name>Zonnecollector</name><value>---</value></item><name>Temperaturen</name></Content>").unwrap();
+        let last_samples = vec![Sample {
+            entity_type: EntityType::Device,
+            entity_name: "Alpha Innotec SWCV 92K3".to_string(),
+            sample_type: SampleType::Temperature,
+            sample_name: "Zonnecollector".to_string(),
+            metric_type: MetricType::Gauge,
+            value: 34.5,
+        }];
 
-        //act
-        let value = websocket_client
-            .get_item_from_response(&"HD".to_string(), &response_message)
+        // act
+        let zero = websocket_client
+            .get_sample(
+                &page,
+                &sample_config_with_missing_value(MissingValuePolicy::Zero),
+                &last_samples,
+                None,
+            )
+            .unwrap();
+        let skip = websocket_client
+            .get_sample(
+                &page,
+                &sample_config_with_missing_value(MissingValuePolicy::Skip),
+                &last_samples,
+                None,
+            )
             .unwrap();
+        let last_known = websocket_client
+            .get_sample(
+                &page,
+                &sample_config_with_missing_value(MissingValuePolicy::LastKnown),
+                &last_samples,
+                None,
+            )
+            .unwrap();
+        let last_known_without_history = websocket_client
+            .get_sample(
+                &page,
+                &sample_config_with_missing_value(MissingValuePolicy::LastKnown),
+                &[],
+                None,
+            )
+            .unwrap();
+        let fail = websocket_client.get_sample(
+            &page,
+            &sample_config_with_missing_value(MissingValuePolicy::Fail),
+            &last_samples,
+            None,
+        );
 
-        assert_eq!(value, 8.10);
+        assert_eq!(zero.unwrap().value, 0.0);
+        assert!(skip.is_none());
+        assert_eq!(last_known.unwrap().value, 34.5);
+        assert!(last_known_without_history.is_none());
+        assert!(fail.is_err());
     }
 
     #[test]
     fn group_sample_configs_per_navigation_returns_hashmap_with_grouped_sample_configs() {
         let sample_configs: Vec<ConfigSample> = vec![
+            ConfigSample::for_test(
+                SampleType::Temperature,
+                "Aanvoer",
+                "Informatie > Temperaturen",
+                "Aanvoer",
+            ),
+            ConfigSample::for_test(
+                SampleType::Temperature,
+                "Retour",
+                "Informatie > Temperaturen",
+                "Retour",
+            ),
             ConfigSample {
-                entity_type: EntityType::Device,
-                entity_name: "Alpha Innotec SWCV 92K3".to_string(),
-                sample_type: SampleType::Temperature,
-                sample_name: "Aanvoer".to_string(),
-                metric_type: MetricType::Gauge,
-                value_multiplier: 1.0,
-                navigation: "Informatie > Temperaturen".to_string(),
-                item: "Aanvoer".to_string(),
-            },
-            ConfigSample {
-                entity_type: EntityType::Device,
-                entity_name: "Alpha Innotec SWCV 92K3".to_string(),
-                sample_type: SampleType::Temperature,
-                sample_name: "Retour".to_string(),
-                metric_type: MetricType::Gauge,
-                value_multiplier: 1.0,
-                navigation: "Informatie > Temperaturen".to_string(),
-                item: "Retour".to_string(),
-            },
-            ConfigSample {
-                entity_type: EntityType::Device,
-                entity_name: "Alpha Innotec SWCV 92K3".to_string(),
-                sample_type: SampleType::Energy,
-                sample_name: "Tapwater".to_string(),
                 metric_type: MetricType::Counter,
                 value_multiplier: 3600000.0,
-                navigation: "Informatie > Energie".to_string(),
-                item: "Warmwater".to_string(),
+                ..ConfigSample::for_test(
+                    SampleType::Energy,
+                    "Tapwater",
+                    "Informatie > Energie",
+                    "Warmwater",
+                )
             },
         ];
 
-        let websocket_client = WebsocketClient::new(
-            WebsocketClientConfig::new("192.168.178.94".to_string(), 8214, "999999".to_string())
-                .unwrap(),
-        );
+        let websocket_client = websocket_client();
 
         let grouped_sample_configs =
             websocket_client.group_sample_configs_per_navigation(sample_configs);
@@ -595,31 +1561,42 @@ mod tests {
         );
     }
 
-    #[test]
+    #[tokio::test]
     #[ignore]
-    fn get_measurement() {
+    async fn get_measurement() {
         let websocket_client = WebsocketClient::new(
-            WebsocketClientConfig::new("192.168.195.4".to_string(), 8214, "999999".to_string())
-                .unwrap(),
+            WebsocketClientConfig::new(
+                "192.168.195.4".to_string(),
+                8214,
+                "999999".to_string(),
+                Timeouts::default(),
+                3,
+            )
+            .unwrap(),
+            None,
+            None,
+            None,
         );
         let config = Config {
             location: "My address".to_string(),
             sanitize_samples: false,
-            sample_configs: vec![ConfigSample {
-                entity_type: EntityType::Device,
-                entity_name: "Alpha Innotec SWCV 92K3".to_string(),
-                sample_type: SampleType::Temperature,
-                sample_name: "Aanvoer".to_string(),
-                metric_type: MetricType::Gauge,
-                value_multiplier: 1.0,
-                navigation: "Informatie > Temperaturen".to_string(),
-                item: "Aanvoer".to_string(),
-            }],
+            language: None,
+            max_failed_samples: None,
+            controllers: vec![],
+            combine_measurements: false,
+            publish_failed_samples: false,
+            sample_configs: vec![ConfigSample::for_test(
+                SampleType::Temperature,
+                "Aanvoer",
+                "Informatie > Temperaturen",
+                "Aanvoer",
+            )],
         };
 
         // act
         let measurements = websocket_client
-            .get_measurements(config, Option::None)
+            .read_measurements(config, Option::None)
+            .await
             .unwrap();
 
         assert_eq!(measurements.len(), 1);
@@ -635,6 +1612,52 @@ mod tests {
         assert_eq!(measurements[0].samples[0].metric_type, MetricType::Gauge);
         // assert_eq!(measurement.samples[0].value, 0.0);
     }
+
+    #[tokio::test]
+    async fn validate_skips_config_without_samples() {
+        let config = Config {
+            location: "My address".to_string(),
+            sanitize_samples: false,
+            language: None,
+            max_failed_samples: None,
+            controllers: vec![],
+            combine_measurements: false,
+            publish_failed_samples: false,
+            sample_configs: vec![],
+        };
+
+        // act
+        let validations = websocket_client().validate(&config).await;
+
+        assert!(validations.is_empty());
+    }
+
+    #[test]
+    fn failed_validations_fails_every_sample() {
+        let sample_configs = vec![
+            ConfigSample::for_test(
+                SampleType::Temperature,
+                "Aanvoer",
+                "Informatie > Temperaturen",
+                "Aanvoer",
+            ),
+            ConfigSample::for_test(
+                SampleType::Temperature,
+                "Retour",
+                "Informatie > Temperaturen",
+                "Retour",
+            ),
+        ];
+
+        // act
+        let validations = failed_validations(&sample_configs, "Connecting to controller failed");
+
+        assert_eq!(validations.len(), 2);
+        assert_eq!(
+            validations[1].result,
+            ValidationResult::Fail("Connecting to controller failed".to_string(), vec![])
+        );
+    }
 }
 
 // func TestGetNavigationFromResponse(t *testing.T) {